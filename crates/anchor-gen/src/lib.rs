@@ -12,6 +12,9 @@
 //!
 //! This will generate a fully functional Rust CPI client for your IDL.
 //!
+//! The Borsh implementations of recursive types are generated for borsh 0.9. Enable the
+//! `compat-borsh-0-10` feature when the crate depends on borsh 0.10, i.e. on anchor-lang 0.28+.
+//!
 //! More examples can be found in the [examples/](https://github.com/saber-hq/anchor-gen/tree/master/examples) directory.
 
 pub use anchor_generate_cpi_crate::generate_cpi_crate;
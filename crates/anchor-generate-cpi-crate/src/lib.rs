@@ -45,5 +45,10 @@ pub fn generate_cpi_crate(input: proc_macro::TokenStream) -> proc_macro::TokenSt
         idl_path: id_literal.value(),
         ..Default::default()
     };
-    opts.to_generator().generate_cpi_interface().into()
+    match opts.try_to_generator() {
+        Ok(generator) => generator
+            .generate_linted_cpi_interface(id_literal.span())
+            .into(),
+        Err(err) => err.to_compile_error(id_literal.span()).into(),
+    }
 }
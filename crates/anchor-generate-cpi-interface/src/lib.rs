@@ -14,8 +14,7 @@
 //!
 //! More examples can be found in the [examples/](https://github.com/saber-hq/anchor-gen/tree/master/examples) directory.
 
-use anchor_idl::GeneratorOptions;
-use darling::FromMeta;
+use anchor_idl::MacroArgs;
 use proc_macro::TokenStream;
 use syn::parse_macro_input;
 
@@ -24,6 +23,26 @@ use syn::parse_macro_input;
 /// # Arguments
 ///
 /// * `idl_path` - Path to a JSON IDL relative to the crate's the Cargo.toml.
+/// * `zero_copy(Type, ...)` - Structs to generate as zero copy. Structs of Anchor 0.30+ IDLs
+///   serialized with bytemuck are zero copy unless the option is given, in which case only the
+///   listed structs are.
+/// * `packed(Type, ...)` - Zero copy structs to generate as `repr(C, packed)`. Structs of
+///   Anchor 0.30+ IDLs keep the packing of their `repr` unless the option is given, in which
+///   case only the listed structs are packed. Their alignment is always kept.
+/// * `aligned_int128` - Generate the `u128` and `i128` fields of zero copy structs as
+///   `PodU128` and `PodI128`, which are aligned like the integers on Solana, so the structs
+///   have the same layout on every target.
+/// * `type_overrides(Type = "path::to::Type", ...)` - External types to use instead of
+///   generating typedefs.
+/// * `derive(Trait, ... => [Type, ...])` - Extra derives of typedefs, other than those the
+///   generated code already has, such as `Clone` and `Debug`.
+/// * `attr("#[...]" => [Type, ...])` - Extra attributes of typedefs.
+/// * `instructions(include = [ix, ...])` / `instructions(exclude = [ix, ...])` - Instructions
+///   to generate, e.g. to leave out the ones which are never invoked. Defaults to all of them.
+///   Typedefs are then only generated if used by the selected instructions, the accounts,
+///   events or constants.
+/// * `keep_types(Type, ...)` - Typedefs to generate even if unused by the selected instructions.
+///   Only valid along with `instructions(...)`.
 ///
 /// # Examples
 ///
@@ -42,12 +61,25 @@ use syn::parse_macro_input;
 /// ```
 #[proc_macro]
 pub fn generate_cpi_interface(input: proc_macro::TokenStream) -> TokenStream {
-    let attr_args = parse_macro_input!(input as syn::AttributeArgs);
-    let parsed = match GeneratorOptions::from_list(&attr_args) {
+    let macro_args = parse_macro_input!(input as MacroArgs);
+    let attr_args = &macro_args.args;
+    let parsed = match macro_args.to_options() {
         Ok(v) => v,
         Err(e) => {
             return TokenStream::from(e.write_errors());
         }
     };
-    parsed.to_generator().generate_cpi_interface().into()
+    match parsed.try_to_generator() {
+        Ok(generator) => {
+            let span = anchor_idl::find_argument_span(attr_args, "idl_path", None)
+                .unwrap_or_else(|| proc_macro::Span::call_site().into());
+            generator.generate_linted_cpi_interface(span).into()
+        }
+        Err(err) => {
+            let span = err
+                .span(attr_args)
+                .unwrap_or_else(|| proc_macro::Span::call_site().into());
+            err.to_compile_error(span).into()
+        }
+    }
 }
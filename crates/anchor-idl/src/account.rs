@@ -15,7 +15,7 @@ pub fn generate_account_fields(
         .iter()
         .map(|account| match account {
             anchor_syn::idl::types::IdlAccountItem::IdlAccount(info) => {
-                let acc_name = format_ident!("{}", info.name.to_snake_case());
+                let acc_name = crate::generate_ident(&info.name.to_snake_case());
                 let annotation = if info.is_mut {
                     quote! { #[account(mut)] }
                 } else {
@@ -31,7 +31,9 @@ pub fn generate_account_fields(
                 } else {
                     acc_type
                 };
+                let docs = crate::generate_docs(info.docs.as_deref());
                 quote! {
+                   #docs
                    #annotation
                    pub #acc_name: #acc_type
                 }
@@ -64,6 +66,7 @@ pub fn generate_account_fields(
     )
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn generate_glam_account_fields(
     name: &str,
     accounts: &[IdlAccountItem],
@@ -83,7 +86,7 @@ pub fn generate_glam_account_fields(
 
     let all_fields = accounts
         .iter()
-        .map(|account| match account {
+        .filter_map(|account| match account {
             anchor_syn::idl::types::IdlAccountItem::IdlAccount(info) => {
                 // account annotation
                 let mut annotation = if info.is_mut {
@@ -116,7 +119,7 @@ pub fn generate_glam_account_fields(
                     acc_type
                 };
 
-                let acc_name = format_ident!("{}", info.name.to_snake_case());
+                let acc_name = crate::generate_ident(&info.name.to_snake_case());
 
                 // result
                 let is_optional = info.is_optional.unwrap_or(false);
@@ -129,9 +132,9 @@ pub fn generate_glam_account_fields(
                         vec![(info.name.to_snake_case(), is_optional)],
                     );
                 }
-                if vault_aliases.contains(&info.name.to_snake_case()) {
-                    None
-                } else if signer_aliases.contains(&info.name.to_snake_case()) {
+                if vault_aliases.contains(&info.name.to_snake_case())
+                    || signer_aliases.contains(&info.name.to_snake_case())
+                {
                     None
                 } else {
                     accounts_to_keep.push(info.name.to_snake_case());
@@ -171,8 +174,6 @@ pub fn generate_glam_account_fields(
                 None
             }
         })
-        .filter(|x| x.is_some())
-        .map(|x| x.unwrap())
         .collect::<Vec<_>>();
 
     vec_accounts_ts.extend(all_fields.clone());
@@ -0,0 +1,534 @@
+use std::collections::BTreeMap;
+
+use anchor_syn::idl::types::{IdlAccountItem, IdlInstruction, IdlPda, IdlSeed};
+use heck::{ToPascalCase, ToSnakeCase};
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+
+use crate::{
+    flatten_accounts, generate_pda_fn, generate_pubkey, get_pda_params, normalize_path,
+    pda_fn_name, FlatAccount, GeneratorError, IdlExtensions,
+};
+
+/// How the client fills in an account left out by the caller.
+pub enum AccountDefault<'a> {
+    /// The account has a fixed address, along with the expression of its public key.
+    Address {
+        address: String,
+        pubkey: TokenStream,
+    },
+    /// The account is a PDA derived from constants, arguments and other accounts.
+    Pda(&'a IdlPda),
+}
+
+/// Lists the accounts of an instruction which the client can fill in, in the order they can
+/// be resolved.
+///
+/// PDAs are only derivable if they do not depend on account data, nor on optional accounts.
+pub fn get_account_defaults<'a>(
+    ix: &'a IdlInstruction,
+    extensions: &IdlExtensions,
+) -> Result<Vec<(FlatAccount<'a>, AccountDefault<'a>)>, GeneratorError> {
+    let flat_accounts = flatten_accounts(&ix.name.to_pascal_case(), "", &ix.accounts);
+    let required: Vec<String> = flat_accounts
+        .iter()
+        .filter(|flat| !flat.account.is_optional.unwrap_or(false))
+        .map(|flat| normalize_path(&flat.path))
+        .collect();
+
+    let mut defaults = vec![];
+    let mut pending = vec![];
+    for flat in flat_accounts
+        .iter()
+        .filter(|flat| !flat.account.is_optional.unwrap_or(false))
+    {
+        if let Some(address) = extensions
+            .account_addresses
+            .get(&(ix.name.clone(), flat.path.clone()))
+        {
+            let pubkey =
+                generate_pubkey(address).ok_or_else(|| GeneratorError::InvalidAddress {
+                    address: address.clone(),
+                    location: format!("instructions.{}.accounts.{}", ix.name, flat.path),
+                })?;
+            defaults.push((
+                flat.clone(),
+                AccountDefault::Address {
+                    address: address.clone(),
+                    pubkey,
+                },
+            ));
+            continue;
+        }
+        let Some(pda) = &flat.account.pda else {
+            continue;
+        };
+        if generate_pda_fn(&ix.name, &flat.path, pda).is_none() {
+            continue;
+        }
+        let dependencies: Option<Vec<String>> = get_pda_params(pda)
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|param| match param.seed {
+                IdlSeed::Account(seed) => {
+                    let path = normalize_path(&seed.path);
+                    Some(required.contains(&path).then_some(path))
+                }
+                _ => None,
+            })
+            .collect();
+        if let Some(dependencies) = dependencies {
+            pending.push((flat, pda, dependencies));
+        }
+    }
+
+    // PDAs may depend on one another, so resolve them until no progress is made.
+    while let Some(index) = pending.iter().position(|(_, _, dependencies)| {
+        dependencies.iter().all(|path| {
+            !pending
+                .iter()
+                .any(|(flat, _, _)| normalize_path(&flat.path) == *path)
+        })
+    }) {
+        let (flat, pda, _) = pending.remove(index);
+        defaults.push((flat.clone(), AccountDefault::Pda(pda)));
+    }
+    Ok(defaults)
+}
+
+/// Generates the client accounts struct of an instruction, along with the structs of its
+/// nested accounts.
+///
+/// Fields are named like in the structs generated by [crate::generate_account_fields].
+/// Accounts the client can fill in are optional, see [get_account_defaults].
+pub fn generate_client_accounts_struct(
+    name: &str,
+    prefix: &str,
+    accounts: &[IdlAccountItem],
+    defaults: &BTreeMap<String, &AccountDefault>,
+) -> TokenStream {
+    let mut all_structs: Vec<TokenStream> = vec![];
+    let mut fields: Vec<TokenStream> = vec![];
+    let mut metas: Vec<TokenStream> = vec![];
+    for account in accounts {
+        match account {
+            IdlAccountItem::IdlAccount(info) => {
+                let acc_name = crate::generate_ident(&info.name.to_snake_case());
+                let docs = crate::generate_docs(info.docs.as_deref());
+                let is_signer = info.is_signer;
+                let new_meta = if info.is_mut {
+                    quote! { AccountMeta::new }
+                } else {
+                    quote! { AccountMeta::new_readonly }
+                };
+                match defaults.get(&format!("{}{}", prefix, info.name)) {
+                    Some(AccountDefault::Address { address, pubkey }) => {
+                        let doc = format!(" Defaults to `{}`.", address);
+                        let address = pubkey;
+                        fields.push(quote! {
+                            #docs
+                            #[doc = #doc]
+                            pub #acc_name: Option<Pubkey>
+                        });
+                        metas.push(quote! {
+                            metas.push(#new_meta(self.#acc_name.unwrap_or(#address), is_signer.unwrap_or(#is_signer)));
+                        });
+                    }
+                    Some(AccountDefault::Pda(_)) => {
+                        let doc = " Derived from its seeds by `resolve` when `None`.";
+                        let expect =
+                            format!("account `{}` is not resolved", info.name.to_snake_case());
+                        fields.push(quote! {
+                            #docs
+                            #[doc = #doc]
+                            pub #acc_name: Option<Pubkey>
+                        });
+                        metas.push(quote! {
+                            metas.push(#new_meta(self.#acc_name.expect(#expect), is_signer.unwrap_or(#is_signer)));
+                        });
+                    }
+                    None if info.is_optional.unwrap_or(false) => {
+                        fields.push(quote! {
+                            #docs
+                            pub #acc_name: Option<Pubkey>
+                        });
+                        // Missing optional accounts are passed as the program ID.
+                        metas.push(quote! {
+                            metas.push(match self.#acc_name {
+                                Some(key) => #new_meta(key, is_signer.unwrap_or(#is_signer)),
+                                None => AccountMeta::new_readonly(crate::ID, false),
+                            });
+                        });
+                    }
+                    None => {
+                        fields.push(quote! {
+                            #docs
+                            pub #acc_name: Pubkey
+                        });
+                        metas.push(quote! {
+                            metas.push(#new_meta(self.#acc_name, is_signer.unwrap_or(#is_signer)));
+                        });
+                    }
+                }
+            }
+            IdlAccountItem::IdlAccounts(inner) => {
+                let field_name = format_ident!("{}_{}", name, inner.name.to_snake_case());
+                let sub_name = format!("{}{}", name, inner.name.to_pascal_case());
+                let sub_ident = format_ident!("{}", &sub_name);
+                all_structs.push(generate_client_accounts_struct(
+                    &sub_name,
+                    &format!("{}{}.", prefix, inner.name),
+                    &inner.accounts,
+                    defaults,
+                ));
+                fields.push(quote! {
+                    pub #field_name: #sub_ident
+                });
+                metas.push(quote! {
+                    metas.extend(self.#field_name.to_account_metas(is_signer));
+                });
+            }
+        }
+    }
+
+    let struct_name = format_ident!("{}", name);
+    quote! {
+        #(#all_structs)*
+
+        #[derive(Clone, Copy, Debug, Default)]
+        pub struct #struct_name {
+            #(#fields),*
+        }
+
+        impl ToAccountMetas for #struct_name {
+            fn to_account_metas(&self, is_signer: Option<bool>) -> Vec<AccountMeta> {
+                let mut metas = vec![];
+                #(#metas)*
+                metas
+            }
+        }
+    }
+}
+
+/// Generates the `resolve` method of the client accounts struct of an instruction, which
+/// fills in the accounts left out by the caller.
+pub fn generate_client_accounts_resolve(
+    ix: &IdlInstruction,
+    defaults: &[(FlatAccount, AccountDefault)],
+) -> TokenStream {
+    let struct_name = format_ident!("{}", ix.name.to_pascal_case());
+    let ix_mod = crate::generate_ident(&ix.name.to_snake_case());
+    let flat_accounts = flatten_accounts(&ix.name.to_pascal_case(), "", &ix.accounts);
+    let key_ident = |path: &str| format_ident!("{}_key", normalize_path(path).replace('.', "_"));
+    let mut resolved: Vec<String> = vec![];
+    let steps = defaults.iter().map(|(flat, default)| {
+        let access = &flat.access;
+        let key = key_ident(&flat.path);
+        let value = match default {
+            AccountDefault::Address { pubkey, .. } => {
+                quote! { accounts.#access.unwrap_or(#pubkey) }
+            }
+            AccountDefault::Pda(pda) => {
+                let fn_name = format_ident!("{}", pda_fn_name(&flat.path));
+                let params = get_pda_params(pda).unwrap_or_default();
+                let values = params.iter().map(|param| {
+                    let value = match param.seed {
+                        IdlSeed::Account(seed) => {
+                            let path = normalize_path(&seed.path);
+                            if resolved.contains(&path) {
+                                let key = key_ident(&path);
+                                quote! { #key }
+                            } else {
+                                let dependency = flat_accounts
+                                    .iter()
+                                    .find(|flat| normalize_path(&flat.path) == path)
+                                    .expect("PDA dependencies are required accounts");
+                                let access = &dependency.access;
+                                quote! { accounts.#access }
+                            }
+                        }
+                        IdlSeed::Arg(seed) => {
+                            let fields = normalize_path(&seed.path);
+                            let fields = fields.split('.').map(crate::generate_ident);
+                            quote! { args.#(#fields).* }
+                        }
+                        IdlSeed::Const(_) => unreachable!(),
+                    };
+                    if param.by_ref {
+                        quote! { &#value }
+                    } else {
+                        value
+                    }
+                });
+                quote! {
+                    accounts.#access.unwrap_or_else(|| pda::#ix_mod::#fn_name(#(#values),*).0)
+                }
+            }
+        };
+        resolved.push(normalize_path(&flat.path));
+        quote! {
+            let #key = #value;
+            accounts.#access = Some(#key);
+        }
+    });
+
+    quote! {
+        impl #struct_name {
+            /// Fills in the accounts left as `None` which have a fixed address or are derived
+            /// from the arguments and the other accounts.
+            #[allow(unused_variables)]
+            pub fn resolve(&self, args: &super::args::#struct_name) -> Self {
+                let mut accounts = *self;
+                #(#steps)*
+                accounts
+            }
+        }
+    }
+}
+
+/// Generates the client args struct of an instruction.
+pub fn generate_client_args_struct(ix: &IdlInstruction, extensions: &IdlExtensions) -> TokenStream {
+    let struct_name = format_ident!("{}", ix.name.to_pascal_case());
+    let fields = crate::generate_fields(&ix.args);
+    let discriminator = extensions.instruction_discriminator(&ix.name);
+    let doc = format!(
+        " Arguments of the `{}` instruction.",
+        ix.name.to_snake_case()
+    );
+    quote! {
+        #[doc = #doc]
+        #[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+        pub struct #struct_name {
+            #fields
+        }
+
+        impl #struct_name {
+            /// Discriminator of the instruction.
+            pub const DISCRIMINATOR: &'static [u8] = &[#(#discriminator),*];
+
+            /// Serializes the instruction data, i.e. the discriminator followed by the arguments.
+            pub fn data(&self) -> Vec<u8> {
+                let mut data = Self::DISCRIMINATOR.to_vec();
+                AnchorSerialize::serialize(self, &mut data).unwrap();
+                data
+            }
+        }
+    }
+}
+
+/// Generates the builder of an instruction.
+///
+/// Accounts left out by the caller are filled in if the accounts struct has a `resolve` method.
+pub fn generate_client_builder(ix: &IdlInstruction, resolve: bool) -> TokenStream {
+    let struct_name = format_ident!("{}", ix.name.to_pascal_case());
+    let fn_name = format_ident!("build_{}", ix.name.to_snake_case());
+    let doc = format!(" Builds a `{}` instruction.", ix.name.to_snake_case());
+    let docs = crate::generate_docs(ix.docs.as_deref());
+    let resolve = if resolve {
+        quote! { let accounts = &accounts.resolve(args); }
+    } else {
+        quote! {}
+    };
+    let separator = if ix.docs.is_some() {
+        quote! { #[doc = ""] }
+    } else {
+        quote! {}
+    };
+    quote! {
+        #[doc = #doc]
+        #separator
+        #docs
+        pub fn #fn_name(
+            accounts: &accounts::#struct_name,
+            args: &args::#struct_name,
+            remaining_accounts: &[AccountMeta],
+        ) -> Instruction {
+            #resolve
+            let mut metas = accounts.to_account_metas(None);
+            metas.extend_from_slice(remaining_accounts);
+            Instruction {
+                program_id: crate::ID,
+                accounts: metas,
+                data: args.data(),
+            }
+        }
+    }
+}
+
+/// Generates off-chain instruction builders.
+pub fn generate_client(
+    ixs: &[IdlInstruction],
+    extensions: &IdlExtensions,
+) -> Result<TokenStream, GeneratorError> {
+    let args_structs = ixs
+        .iter()
+        .map(|ix| generate_client_args_struct(ix, extensions));
+    let mut accounts_structs = vec![];
+    let mut builders = vec![];
+    for ix in ixs {
+        let defaults = get_account_defaults(ix, extensions)?;
+        let defaults_by_path = defaults
+            .iter()
+            .map(|(flat, default)| (flat.path.clone(), default))
+            .collect();
+        accounts_structs.push(generate_client_accounts_struct(
+            &ix.name.to_pascal_case(),
+            "",
+            &ix.accounts,
+            &defaults_by_path,
+        ));
+        if !defaults.is_empty() {
+            accounts_structs.push(generate_client_accounts_resolve(ix, &defaults));
+        }
+        builders.push(generate_client_builder(ix, !defaults.is_empty()));
+    }
+    Ok(quote! {
+        use anchor_lang::solana_program::instruction::Instruction;
+
+        pub mod args {
+            //! Arguments of instructions.
+            use super::*;
+            #(#args_structs)*
+        }
+
+        #[allow(non_snake_case)]
+        pub mod accounts {
+            //! Accounts of instructions.
+            use super::*;
+            #(#accounts_structs)*
+        }
+
+        #(#builders)*
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const IDL: &str = r#"{
+        "version": "0.1.0",
+        "name": "test",
+        "instructions": [
+            {
+                "name": "openPosition",
+                "accounts": [
+                    { "name": "owner", "isMut": true, "isSigner": true },
+                    {
+                        "name": "position",
+                        "isMut": true,
+                        "isSigner": false,
+                        "pda": {
+                            "seeds": [
+                                { "kind": "const", "type": "string", "value": "position" },
+                                { "kind": "account", "type": "publicKey", "path": "positionMint" }
+                            ]
+                        }
+                    },
+                    {
+                        "name": "positionMint",
+                        "isMut": true,
+                        "isSigner": false,
+                        "pda": {
+                            "seeds": [
+                                { "kind": "account", "type": "publicKey", "path": "owner" },
+                                { "kind": "arg", "type": "u16", "path": "index" }
+                            ]
+                        }
+                    },
+                    {
+                        "name": "delegated",
+                        "isMut": false,
+                        "isSigner": false,
+                        "pda": {
+                            "seeds": [
+                                { "kind": "account", "type": "publicKey", "path": "delegate" }
+                            ]
+                        }
+                    },
+                    { "name": "delegate", "isMut": false, "isSigner": false, "isOptional": true },
+                    {
+                        "name": "pool",
+                        "isMut": false,
+                        "isSigner": false,
+                        "pda": {
+                            "seeds": [
+                                {
+                                    "kind": "account",
+                                    "type": "publicKey",
+                                    "account": "Position",
+                                    "path": "position.pool"
+                                }
+                            ]
+                        }
+                    },
+                    {
+                        "name": "programs",
+                        "accounts": [
+                            { "name": "systemProgram", "isMut": false, "isSigner": false }
+                        ]
+                    }
+                ],
+                "args": [{ "name": "index", "type": "u16" }]
+            }
+        ]
+    }"#;
+
+    fn extensions(address: &str) -> IdlExtensions {
+        let mut extensions = IdlExtensions::default();
+        extensions.account_addresses.insert(
+            (
+                "openPosition".to_string(),
+                "programs.systemProgram".to_string(),
+            ),
+            address.to_string(),
+        );
+        extensions
+    }
+
+    #[test]
+    fn account_defaults() {
+        let (idl, _) = crate::parse_idl(IDL).unwrap();
+        let extensions = extensions("11111111111111111111111111111111");
+        let defaults = get_account_defaults(&idl.instructions[0], &extensions).unwrap();
+        let defaults: Vec<(&str, Option<&str>)> = defaults
+            .iter()
+            .map(|(flat, default)| {
+                let address = match default {
+                    AccountDefault::Address { address, .. } => Some(address.as_str()),
+                    AccountDefault::Pda(_) => None,
+                };
+                (flat.path.as_str(), address)
+            })
+            .collect();
+        // PDAs come after the PDAs they depend on, while PDAs depending on optional
+        // accounts or on account data are left to the caller.
+        assert_eq!(
+            defaults,
+            [
+                (
+                    "programs.systemProgram",
+                    Some("11111111111111111111111111111111")
+                ),
+                ("positionMint", None),
+                ("position", None),
+            ]
+        );
+    }
+
+    #[test]
+    fn account_defaults_reject_invalid_addresses() {
+        let (idl, _) = crate::parse_idl(IDL).unwrap();
+        let extensions = extensions("not-an-address");
+        match get_account_defaults(&idl.instructions[0], &extensions) {
+            Err(GeneratorError::InvalidAddress { address, location }) => {
+                assert_eq!(address, "not-an-address");
+                assert_eq!(
+                    location,
+                    "instructions.openPosition.accounts.programs.systemProgram"
+                );
+            }
+            _ => panic!("expected an invalid address"),
+        }
+    }
+}
@@ -0,0 +1,75 @@
+use anchor_syn::idl::types::{IdlConst, IdlType};
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+
+use crate::GeneratorError;
+
+/// Generates the type of a constant.
+///
+/// Byte strings and strings are borrowed, since they are emitted as literals.
+fn generate_const_type(constant: &IdlConst) -> Result<TokenStream, GeneratorError> {
+    match &constant.ty {
+        IdlType::Bytes => Ok(quote! { &[u8] }),
+        IdlType::String => Ok(quote! { &str }),
+        ty => crate::ty_to_rust_type(ty)
+            .parse()
+            .map_err(|_| GeneratorError::InvalidConstant {
+                name: constant.name.clone(),
+                value: constant.value.clone(),
+            }),
+    }
+}
+
+/// Generates the value of a constant.
+///
+/// Legacy IDLs store values as Rust expressions (e.g. `b"seed"`), while Anchor 0.30+ IDLs
+/// store their `Debug` representation (e.g. `[115, 101, 101, 100]`).
+pub fn try_generate_const_value(constant: &IdlConst) -> Result<TokenStream, GeneratorError> {
+    let value = constant.value.trim();
+    let invalid = || GeneratorError::InvalidConstant {
+        name: constant.name.clone(),
+        value: value.to_string(),
+    };
+    Ok(match &constant.ty {
+        IdlType::Bytes if value.starts_with('[') => {
+            let stream: TokenStream = value.parse().map_err(|_| invalid())?;
+            quote! { &#stream }
+        }
+        IdlType::Bytes if value.starts_with('"') => {
+            let stream: TokenStream = value.parse().map_err(|_| invalid())?;
+            quote! { #stream.as_bytes() }
+        }
+        IdlType::String if !value.starts_with('"') => quote! { #value },
+        IdlType::U256 | IdlType::I256 => {
+            let bytes = crate::parse_int256(value).ok_or_else(invalid)?;
+            let ty = generate_const_type(constant)?;
+            quote! { #ty::from_le_bytes([#(#bytes),*]) }
+        }
+        IdlType::PublicKey => {
+            let address = value
+                .trim_start_matches("pubkey!(")
+                .trim_end_matches(')')
+                .trim_matches('"');
+            crate::generate_pubkey(address).ok_or_else(invalid)?
+        }
+        _ => value.parse().map_err(|_| invalid())?,
+    })
+}
+
+/// Generates constants.
+pub fn generate_constants(constants: &[IdlConst]) -> Result<TokenStream, GeneratorError> {
+    let defined = constants
+        .iter()
+        .map(|constant| {
+            let name = format_ident!("{}", constant.name);
+            let ty = generate_const_type(constant)?;
+            let value = try_generate_const_value(constant)?;
+            Ok(quote! {
+                pub const #name: #ty = #value;
+            })
+        })
+        .collect::<Result<Vec<_>, GeneratorError>>()?;
+    Ok(quote! {
+        #(#defined)*
+    })
+}
@@ -0,0 +1,180 @@
+use std::collections::BTreeMap;
+
+use anchor_syn::idl::types::{IdlAccountItem, IdlInstruction, IdlTypeDefinition};
+use heck::{ToPascalCase, ToSnakeCase};
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+
+use crate::{IdlExtensions, StructOpts};
+
+/// Lists the names of the accounts of an instruction in the order they are passed.
+///
+/// Nested accounts are named by their path, e.g. `group.account`.
+pub fn get_account_names(accounts: &[IdlAccountItem]) -> Vec<String> {
+    accounts
+        .iter()
+        .flat_map(|account| match account {
+            IdlAccountItem::IdlAccount(info) => vec![info.name.to_snake_case()],
+            IdlAccountItem::IdlAccounts(inner) => get_account_names(&inner.accounts)
+                .into_iter()
+                .map(|name| format!("{}.{}", inner.name.to_snake_case(), name))
+                .collect(),
+        })
+        .collect()
+}
+
+/// Generates the `Instruction` decoder.
+///
+/// Variants wrap the args structs of the `client` module.
+pub fn generate_ix_decoder(ixs: &[IdlInstruction], extensions: &IdlExtensions) -> TokenStream {
+    let variants = ixs.iter().map(|ix| {
+        let name = format_ident!("{}", ix.name.to_pascal_case());
+        quote! {
+            #name(client::args::#name)
+        }
+    });
+    let decode_arms = ixs.iter().map(|ix| {
+        let name = format_ident!("{}", ix.name.to_pascal_case());
+        let discriminator = extensions.instruction_discriminator(&ix.name);
+        quote! {
+            if let Some(mut data) = data.strip_prefix(&[#(#discriminator),*][..]) {
+                return client::args::#name::deserialize(&mut data).ok().map(Self::#name);
+            }
+        }
+    });
+    let name_arms = ixs.iter().map(|ix| {
+        let name = format_ident!("{}", ix.name.to_pascal_case());
+        let name_str = ix.name.to_snake_case();
+        quote! {
+            Self::#name(_) => #name_str
+        }
+    });
+    let account_names_arms = ixs.iter().map(|ix| {
+        let name = format_ident!("{}", ix.name.to_pascal_case());
+        let account_names = get_account_names(&ix.accounts);
+        quote! {
+            Self::#name(_) => &[#(#account_names),*]
+        }
+    });
+
+    quote! {
+        /// An instruction of the program, decoded from its data.
+        #[derive(Clone, Debug)]
+        pub enum Instruction {
+            #(#variants),*
+        }
+
+        impl Instruction {
+            /// Decodes an instruction from its discriminator-prefixed data.
+            pub fn decode(data: &[u8]) -> Option<Self> {
+                #(#decode_arms)*
+                None
+            }
+
+            /// Name of the instruction.
+            pub fn name(&self) -> &'static str {
+                match self {
+                    #(#name_arms),*
+                }
+            }
+
+            /// Names of the accounts of the instruction, in the order they are passed.
+            pub fn account_names(&self) -> &'static [&'static str] {
+                match self {
+                    #(#account_names_arms),*
+                }
+            }
+
+            /// Pairs the account keys of the instruction with their names.
+            ///
+            /// Keys past the accounts of the instruction are named `remaining`.
+            pub fn named_accounts<'a, T>(&self, keys: &'a [T]) -> Vec<(&'static str, &'a T)> {
+                let names = self.account_names();
+                keys.iter()
+                    .enumerate()
+                    .map(|(index, key)| (names.get(index).copied().unwrap_or("remaining"), key))
+                    .collect()
+            }
+        }
+    }
+}
+
+/// Generates the `ProgramAccount` decoder.
+///
+/// Zero copy accounts are boxed, as they tend to be large.
+pub fn generate_account_decoder(
+    account_defs: &[IdlTypeDefinition],
+    struct_opts: &BTreeMap<String, StructOpts>,
+    extensions: &IdlExtensions,
+) -> TokenStream {
+    let is_zero_copy = |def: &IdlTypeDefinition| {
+        struct_opts
+            .get(&def.name)
+            .map(|opts| opts.zero_copy)
+            .unwrap_or_default()
+    };
+    let variants = account_defs.iter().map(|def| {
+        let name = format_ident!("{}", def.name);
+        if is_zero_copy(def) {
+            quote! {
+                #name(Box<#name>)
+            }
+        } else {
+            quote! {
+                #name(#name)
+            }
+        }
+    });
+    let decode_arms = account_defs.iter().map(|def| {
+        let name = format_ident!("{}", def.name);
+        let discriminator = extensions.account_discriminator(&def.name);
+        let decode = if is_zero_copy(def) {
+            quote! {
+                let data = data.get(..std::mem::size_of::<#name>())?;
+                return anchor_lang::__private::bytemuck::try_pod_read_unaligned::<#name>(data)
+                    .ok()
+                    .map(|account| ProgramAccount::#name(Box::new(account)));
+            }
+        } else {
+            quote! {
+                return #name::deserialize(&mut data).ok().map(ProgramAccount::#name);
+            }
+        };
+        quote! {
+            #[allow(unused_mut)]
+            if let Some(mut data) = data.strip_prefix(&[#(#discriminator),*][..]) {
+                #decode
+            }
+        }
+    });
+    let name_arms = account_defs.iter().map(|def| {
+        let name = format_ident!("{}", def.name);
+        let name_str = &def.name;
+        quote! {
+            Self::#name(_) => #name_str
+        }
+    });
+
+    quote! {
+        /// An account of the program, decoded from its data.
+        #[derive(Clone, Debug)]
+        pub enum ProgramAccount {
+            #(#variants),*
+        }
+
+        impl ProgramAccount {
+            /// Name of the account.
+            pub fn name(&self) -> &'static str {
+                match self {
+                    #(#name_arms),*
+                }
+            }
+        }
+
+        /// Decodes an account from its discriminator-prefixed data.
+        pub fn decode_account(data: &[u8]) -> Option<ProgramAccount> {
+            #(#decode_arms)*
+            None
+        }
+    }
+}
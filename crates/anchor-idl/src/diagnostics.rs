@@ -0,0 +1,606 @@
+use std::{collections::BTreeMap, fmt, io, path::PathBuf};
+
+use anchor_syn::idl::types::{
+    EnumFields, Idl, IdlDefinedTypeArg, IdlField, IdlType, IdlTypeDefinition, IdlTypeDefinitionTy,
+};
+use heck::ToSnakeCase;
+use proc_macro2::{Span, TokenStream};
+use syn::{spanned::Spanned, Meta, NestedMeta};
+
+use crate::{IdlExtensions, StructOpts};
+
+/// An error preventing code generation, with the context needed to locate its cause.
+#[derive(Debug)]
+pub enum GeneratorError {
+    /// The IDL file could not be read.
+    ReadIdl { path: PathBuf, source: io::Error },
+    /// The IDL file is not a valid IDL.
+    ParseIdl {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+    /// The GLAM codegen config could not be read.
+    ReadConfig { path: PathBuf, source: io::Error },
+    /// The GLAM codegen config is not valid YAML, or its entries are invalid.
+    ParseConfig {
+        path: PathBuf,
+        source: serde_yaml::Error,
+    },
+    /// The GLAM codegen config has no entry for the program.
+    MissingProgramConfig { path: PathBuf, program: String },
+    /// A macro argument is not of the expected form.
+    InvalidArgument {
+        argument: &'static str,
+        message: String,
+    },
+    /// A macro argument names a struct which is not defined by the IDL.
+    UnknownStruct {
+        argument: &'static str,
+        name: String,
+    },
+    /// A type override names a typedef which is not a type of the IDL.
+    UnknownOverride { name: String },
+    /// A type override does not refer to a path.
+    InvalidOverride { name: String, path: String },
+    /// A macro argument names a typedef which is not defined by the IDL.
+    UnknownTypedef {
+        argument: &'static str,
+        name: String,
+        span: Span,
+    },
+    /// A macro argument adds derives to a type alias.
+    AliasDerive {
+        argument: &'static str,
+        name: String,
+        span: Span,
+    },
+    /// A macro argument derives a trait which the generated code already derives or
+    /// implements for a typedef.
+    DuplicateDerive {
+        argument: &'static str,
+        name: String,
+        derive: String,
+        span: Span,
+    },
+    /// The `instructions` option names an instruction which is not defined by the IDL.
+    UnknownInstruction { name: String, span: Span },
+    /// A type refers to a typedef which is not defined by the IDL.
+    UnknownType {
+        name: String,
+        /// Path of the type in the IDL, e.g. `types.Pool.fields.config`.
+        location: String,
+    },
+    /// A zero copy struct has a field which is not plain old data.
+    NonPodField {
+        name: String,
+        field: String,
+        /// Description of the offending type.
+        reason: String,
+    },
+    /// A constant has a value which cannot be represented by its type.
+    InvalidConstant { name: String, value: String },
+    /// An account has an address which is not a valid public key.
+    InvalidAddress { address: String, location: String },
+    /// An item has a name which is not a valid Rust identifier.
+    InvalidName {
+        name: String,
+        /// Path of the item in the IDL, e.g. `types.Pool.fields`.
+        location: String,
+    },
+    /// A const generic argument is not a valid Rust expression.
+    InvalidTypeArgument { value: String, location: String },
+    /// An account is defined as a type alias, which cannot be generated.
+    AliasAccount { name: String },
+    /// An instruction has a discriminator other than the one `#[program]` derives from its
+    /// name, so its CPI cannot be generated.
+    CustomDiscriminator {
+        name: String,
+        discriminator: Vec<u8>,
+        expected: Vec<u8>,
+    },
+    /// An instruction is named after a Rust keyword, so the discriminator `#[program]` derives
+    /// from the name of its handler is not the one of the instruction.
+    KeywordInstruction {
+        name: String,
+        handler: String,
+        discriminator: Vec<u8>,
+        derived: Vec<u8>,
+    },
+    /// An account or an event has a discriminator which is not 8 bytes long, which the
+    /// `Discriminator` trait cannot represent.
+    InvalidDiscriminatorLength {
+        /// Kind of the item, i.e. `account` or `event`.
+        kind: &'static str,
+        name: String,
+        discriminator: Vec<u8>,
+    },
+}
+
+impl fmt::Display for GeneratorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GeneratorError::ReadIdl { path, source } => {
+                write!(f, "failed to read IDL `{}`: {}", path.display(), source)
+            }
+            GeneratorError::ParseIdl { path, source } => {
+                write!(f, "failed to parse IDL `{}`: {}", path.display(), source)
+            }
+            GeneratorError::ReadConfig { path, source } => write!(
+                f,
+                "failed to read codegen config `{}`: {}",
+                path.display(),
+                source
+            ),
+            GeneratorError::ParseConfig { path, source } => write!(
+                f,
+                "failed to parse codegen config `{}`: {}",
+                path.display(),
+                source
+            ),
+            GeneratorError::MissingProgramConfig { path, program } => write!(
+                f,
+                "codegen config `{}` has no entry for program `{}`",
+                path.display(),
+                program
+            ),
+            GeneratorError::InvalidArgument { argument, message } => {
+                write!(f, "invalid `{}` argument: {}", argument, message)
+            }
+            GeneratorError::UnknownStruct { argument, name } => write!(
+                f,
+                "`{}` lists `{}`, which is not a struct of the IDL",
+                argument, name
+            ),
+            GeneratorError::UnknownOverride { name } => write!(
+                f,
+                "`type_overrides` lists `{}`, which is not a type of the IDL",
+                name
+            ),
+            GeneratorError::InvalidOverride { name, path } => write!(
+                f,
+                "`type_overrides` maps `{}` to `{}`, which is not a path such as \
+                 `crate::types::{}`",
+                name, path, name
+            ),
+            GeneratorError::UnknownTypedef { argument, name, .. } => write!(
+                f,
+                "`{}` lists `{}`, which is not a type of the IDL",
+                argument, name
+            ),
+            GeneratorError::AliasDerive { argument, name, .. } => write!(
+                f,
+                "`{}` adds derives to `{}`, which is a type alias",
+                argument, name
+            ),
+            GeneratorError::DuplicateDerive {
+                argument,
+                name,
+                derive,
+                ..
+            } => write!(
+                f,
+                "`{}` derives `{}` for `{}`, which the generated code already implements",
+                argument, derive, name
+            ),
+            GeneratorError::UnknownInstruction { name, .. } => write!(
+                f,
+                "`instructions` lists `{}`, which is not an instruction of the IDL",
+                name
+            ),
+            GeneratorError::UnknownType { name, location } => {
+                write!(f, "type `{}` at `{}` is not defined", name, location)
+            }
+            GeneratorError::NonPodField {
+                name,
+                field,
+                reason,
+            } => write!(
+                f,
+                "zero copy struct `{}` cannot hold {} (at field `{}`)",
+                name, reason, field
+            ),
+            GeneratorError::InvalidConstant { name, value } => {
+                write!(f, "invalid value `{}` for constant `{}`", value, name)
+            }
+            GeneratorError::InvalidAddress { address, location } => {
+                write!(f, "invalid address `{}` at `{}`", address, location)
+            }
+            GeneratorError::InvalidTypeArgument { value, location } => write!(
+                f,
+                "const argument `{}` at `{}` is not a valid Rust expression",
+                value, location
+            ),
+            GeneratorError::InvalidName { name, location } => write!(
+                f,
+                "name `{}` at `{}` is not a valid Rust identifier",
+                name, location
+            ),
+            GeneratorError::CustomDiscriminator {
+                name,
+                discriminator,
+                expected,
+            } => write!(
+                f,
+                "instruction `{}` has the custom discriminator {:?}, but the CPI generated by \
+                 `#[program]` uses {:?}; exclude it with `instructions(exclude = [...])`",
+                name, discriminator, expected
+            ),
+            GeneratorError::KeywordInstruction {
+                name,
+                handler,
+                discriminator,
+                derived,
+            } => write!(
+                f,
+                "instruction `{}` is a Rust keyword, so `#[program]` generates its CPI as `{}` \
+                 with the discriminator {:?} instead of {:?}; exclude it with \
+                 `instructions(exclude = [...])`",
+                name, handler, derived, discriminator
+            ),
+            GeneratorError::InvalidDiscriminatorLength {
+                kind,
+                name,
+                discriminator,
+            } => write!(
+                f,
+                "{} `{}` has the {}-byte discriminator {:?}, but only 8-byte discriminators \
+                 are supported",
+                kind,
+                name,
+                discriminator.len(),
+                discriminator
+            ),
+            GeneratorError::AliasAccount { name } => {
+                write!(
+                    f,
+                    "account `{}` is a type alias, which is not supported",
+                    name
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for GeneratorError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            GeneratorError::ReadIdl { source, .. } | GeneratorError::ReadConfig { source, .. } => {
+                Some(source)
+            }
+            GeneratorError::ParseIdl { source, .. } => Some(source),
+            GeneratorError::ParseConfig { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl GeneratorError {
+    /// Name of the macro argument causing the error.
+    pub fn argument(&self) -> &'static str {
+        match self {
+            GeneratorError::ReadConfig { .. }
+            | GeneratorError::ParseConfig { .. }
+            | GeneratorError::MissingProgramConfig { .. } => "glam_codegen_config",
+            GeneratorError::UnknownOverride { .. } | GeneratorError::InvalidOverride { .. } => {
+                "type_overrides"
+            }
+            GeneratorError::UnknownInstruction { .. } => "instructions",
+            GeneratorError::NonPodField { .. } => "zero_copy",
+            GeneratorError::InvalidArgument { argument, .. }
+            | GeneratorError::UnknownStruct { argument, .. }
+            | GeneratorError::UnknownTypedef { argument, .. }
+            | GeneratorError::AliasDerive { argument, .. }
+            | GeneratorError::DuplicateDerive { argument, .. } => argument,
+            _ => "idl_path",
+        }
+    }
+
+    /// Finds the span of the macro argument causing the error.
+    ///
+    /// For errors about an item of a list, this is the span of the item.
+    pub fn span(&self, args: &[NestedMeta]) -> Option<Span> {
+        if let GeneratorError::UnknownTypedef { span, .. }
+        | GeneratorError::AliasDerive { span, .. }
+        | GeneratorError::DuplicateDerive { span, .. }
+        | GeneratorError::UnknownInstruction { span, .. } = self
+        {
+            return Some(*span);
+        }
+        let item = match self {
+            GeneratorError::UnknownStruct { name, .. }
+            | GeneratorError::UnknownOverride { name }
+            | GeneratorError::InvalidOverride { name, .. }
+            | GeneratorError::NonPodField { name, .. } => Some(name.as_str()),
+            _ => None,
+        };
+        find_argument_span(args, self.argument(), item)
+    }
+
+    /// Generates a `compile_error!` invocation reporting the error at `span`.
+    pub fn to_compile_error(&self, span: Span) -> TokenStream {
+        syn::Error::new(span, self).to_compile_error()
+    }
+}
+
+/// Finds the span of a macro argument, or of an item of its list if `item` is provided and
+/// listed.
+pub fn find_argument_span(args: &[NestedMeta], argument: &str, item: Option<&str>) -> Option<Span> {
+    let meta = args.iter().find_map(|arg| match arg {
+        NestedMeta::Meta(meta) if meta.path().is_ident(argument) => Some(meta),
+        _ => None,
+    })?;
+    if let (Some(item), Meta::List(list)) = (item, meta) {
+        let nested = list.nested.iter().find(|nested| match nested {
+            NestedMeta::Meta(meta) => meta.path().is_ident(item),
+            _ => false,
+        });
+        if let Some(nested) = nested {
+            return Some(nested.span());
+        }
+    }
+    Some(meta.span())
+}
+
+/// Checks that the types of `ty` are defined, where `generics` are the generic parameters
+/// in scope.
+fn validate_type(
+    defs: &[IdlTypeDefinition],
+    generics: &[String],
+    ty: &IdlType,
+    location: &str,
+) -> Result<(), GeneratorError> {
+    match ty {
+        IdlType::Option(inner)
+        | IdlType::Vec(inner)
+        | IdlType::Array(inner, _)
+        | IdlType::GenericLenArray(inner, _) => validate_type(defs, generics, inner, location),
+        IdlType::Defined(name) | IdlType::DefinedWithTypeArgs { name, .. }
+            if !defs.iter().any(|def| def.name == *name) =>
+        {
+            Err(GeneratorError::UnknownType {
+                name: name.clone(),
+                location: location.to_string(),
+            })
+        }
+        IdlType::DefinedWithTypeArgs { args, .. } => args.iter().try_for_each(|arg| match arg {
+            IdlDefinedTypeArg::Type(ty) => validate_type(defs, generics, ty, location),
+            IdlDefinedTypeArg::Generic(name) if !generics.contains(name) => {
+                Err(GeneratorError::UnknownType {
+                    name: name.clone(),
+                    location: location.to_string(),
+                })
+            }
+            IdlDefinedTypeArg::Value(value) if value.parse::<TokenStream>().is_err() => {
+                Err(GeneratorError::InvalidTypeArgument {
+                    value: value.clone(),
+                    location: location.to_string(),
+                })
+            }
+            _ => Ok(()),
+        }),
+        IdlType::Generic(name) if !generics.contains(name) => Err(GeneratorError::UnknownType {
+            name: name.clone(),
+            location: location.to_string(),
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// Checks that a name can be converted to an identifier.
+fn validate_name(name: &str, location: &str) -> Result<(), GeneratorError> {
+    let mut chars = name.chars();
+    let valid = chars
+        .next()
+        .is_some_and(|first| first.is_alphabetic() || first == '_')
+        && chars.all(|c| c.is_alphanumeric() || c == '_')
+        && name != "_";
+    if valid {
+        Ok(())
+    } else {
+        Err(GeneratorError::InvalidName {
+            name: name.to_string(),
+            location: location.to_string(),
+        })
+    }
+}
+
+fn validate_fields(
+    defs: &[IdlTypeDefinition],
+    generics: &[String],
+    fields: &[IdlField],
+    location: &str,
+) -> Result<(), GeneratorError> {
+    fields.iter().try_for_each(|field| {
+        // Fields of tuple structs are named after their index.
+        if field.name.parse::<usize>().is_err() {
+            validate_name(&field.name, location)?;
+        }
+        validate_type(
+            defs,
+            generics,
+            &field.ty,
+            &format!("{}.{}", location, field.name),
+        )
+    })
+}
+
+fn validate_typedef(
+    defs: &[IdlTypeDefinition],
+    def: &IdlTypeDefinition,
+    location: &str,
+) -> Result<(), GeneratorError> {
+    let generics = def.generics.as_deref().unwrap_or_default();
+    validate_name(&def.name, location)?;
+    for generic in generics {
+        validate_name(generic, &format!("{}.generics", location))?;
+    }
+    match &def.ty {
+        IdlTypeDefinitionTy::Struct { fields } => {
+            validate_fields(defs, generics, fields, &format!("{}.fields", location))
+        }
+        IdlTypeDefinitionTy::Enum { variants } => variants.iter().try_for_each(|variant| {
+            validate_name(&variant.name, &format!("{}.variants", location))?;
+            let location = format!("{}.variants.{}", location, variant.name);
+            match &variant.fields {
+                Some(EnumFields::Named(fields)) => {
+                    validate_fields(defs, generics, fields, &location)
+                }
+                Some(EnumFields::Tuple(types)) => {
+                    types.iter().enumerate().try_for_each(|(index, ty)| {
+                        validate_type(defs, generics, ty, &format!("{}.{}", location, index))
+                    })
+                }
+                None => Ok(()),
+            }
+        }),
+        IdlTypeDefinitionTy::Alias { value } => validate_type(defs, generics, value, location),
+    }
+}
+
+fn validate_address(address: &str, location: String) -> Result<(), GeneratorError> {
+    match crate::generate_pubkey(address) {
+        Some(_) => Ok(()),
+        None => Err(GeneratorError::InvalidAddress {
+            address: address.to_string(),
+            location,
+        }),
+    }
+}
+
+/// Checks that the discriminator of an account or an event fits the `Discriminator` trait.
+fn validate_discriminator_length(
+    kind: &'static str,
+    name: &str,
+    discriminator: Option<&Vec<u8>>,
+) -> Result<(), GeneratorError> {
+    match discriminator {
+        Some(discriminator) if discriminator.len() != 8 => {
+            Err(GeneratorError::InvalidDiscriminatorLength {
+                kind,
+                name: name.to_string(),
+                discriminator: discriminator.clone(),
+            })
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Checks that the instructions can be generated with `#[program]`, which derives their
+/// discriminators from their names.
+///
+/// This runs after the instruction filter, so that the instructions it rejects can be excluded.
+pub fn validate_instruction_discriminators(
+    idl: &Idl,
+    extensions: &IdlExtensions,
+) -> Result<(), GeneratorError> {
+    for ix in &idl.instructions {
+        let expected = crate::sighash("global", &ix.name.to_snake_case()).to_vec();
+        if let Some(discriminator) = extensions.instruction_discriminators.get(&ix.name) {
+            if *discriminator != expected {
+                return Err(GeneratorError::CustomDiscriminator {
+                    name: ix.name.clone(),
+                    discriminator: discriminator.clone(),
+                    expected,
+                });
+            }
+        }
+        let handler = crate::generate_handler_ident(&ix.name).to_string();
+        let derived = crate::sighash("global", &handler).to_vec();
+        if derived != expected {
+            return Err(GeneratorError::KeywordInstruction {
+                name: ix.name.clone(),
+                handler,
+                discriminator: expected,
+                derived,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Validates the parts of an IDL the generator relies on, so that generating code from it
+/// does not fail.
+pub fn validate_idl(idl: &Idl, extensions: &IdlExtensions) -> Result<(), GeneratorError> {
+    let defs = crate::get_resolvable_typedefs(idl);
+    for def in &idl.types {
+        validate_typedef(&defs, def, &format!("types.{}", def.name))?;
+    }
+    for def in &idl.accounts {
+        if let IdlTypeDefinitionTy::Alias { .. } = def.ty {
+            return Err(GeneratorError::AliasAccount {
+                name: def.name.clone(),
+            });
+        }
+        validate_typedef(&defs, def, &format!("accounts.{}", def.name))?;
+        validate_discriminator_length(
+            "account",
+            &def.name,
+            extensions.account_discriminators.get(&def.name),
+        )?;
+    }
+    for ix in &idl.instructions {
+        validate_name(&ix.name, "instructions")?;
+        let location = format!("instructions.{}", ix.name);
+        validate_fields(&defs, &[], &ix.args, &format!("{}.args", location))?;
+        if let Some(returns) = &ix.returns {
+            validate_type(&defs, &[], returns, &format!("{}.returns", location))?;
+        }
+    }
+    for event in idl.events.iter().flatten() {
+        validate_name(&event.name, "events")?;
+        validate_discriminator_length(
+            "event",
+            &event.name,
+            extensions.event_discriminators.get(&event.name),
+        )?;
+        event.fields.iter().try_for_each(|field| {
+            validate_name(&field.name, &format!("events.{}.fields", event.name))?;
+            validate_type(
+                &defs,
+                &[],
+                &field.ty,
+                &format!("events.{}.fields.{}", event.name, field.name),
+            )
+        })?;
+    }
+    for constant in &idl.constants {
+        validate_name(&constant.name, "constants")?;
+        let location = format!("constants.{}", constant.name);
+        validate_type(&defs, &[], &constant.ty, &location)?;
+        crate::try_generate_const_value(constant)?;
+    }
+    for ((ix_name, path), address) in &extensions.account_addresses {
+        validate_address(
+            address,
+            format!("instructions.{}.accounts.{}", ix_name, path),
+        )?;
+    }
+    Ok(())
+}
+
+/// Checks that zero copy structs only hold plain old data, as `bytemuck` requires of them.
+pub fn validate_struct_opts(
+    idl: &Idl,
+    struct_opts: &BTreeMap<String, StructOpts>,
+) -> Result<(), GeneratorError> {
+    let defs = crate::get_resolvable_typedefs(idl);
+    for (name, opts) in struct_opts {
+        let def = defs.iter().find(|def| def.name == *name);
+        let (true, Some(IdlTypeDefinitionTy::Struct { fields })) =
+            (opts.zero_copy, def.map(|def| &def.ty))
+        else {
+            continue;
+        };
+        for field in fields {
+            let mut visited = vec![name.clone()];
+            if let Some(reason) = crate::find_non_pod(&defs, struct_opts, &field.ty, &mut visited) {
+                return Err(GeneratorError::NonPodField {
+                    name: name.clone(),
+                    field: field.name.clone(),
+                    reason,
+                });
+            }
+        }
+    }
+    Ok(())
+}
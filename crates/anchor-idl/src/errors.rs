@@ -0,0 +1,134 @@
+use anchor_syn::idl::types::IdlErrorCode;
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+
+/// Generates the error enum of a program.
+///
+/// The enum mirrors what `#[error_code]` generates, but keeps the exact codes of the IDL,
+/// which need not be contiguous.
+pub fn generate_errors(errors: &[IdlErrorCode]) -> TokenStream {
+    let variants = errors.iter().map(|e| {
+        let name = format_ident!("{}", e.name);
+        let code = e.code;
+        let doc = format!(" {}", e.msg.as_deref().unwrap_or(&e.name));
+        quote! {
+            #[doc = #doc]
+            #name = #code
+        }
+    });
+    let from_code_arms = errors.iter().map(|e| {
+        let name = format_ident!("{}", e.name);
+        let code = e.code;
+        quote! {
+            #code => Some(Self::#name)
+        }
+    });
+    let name_arms = errors.iter().map(|e| {
+        let name = format_ident!("{}", e.name);
+        let name_str = &e.name;
+        quote! {
+            Self::#name => #name_str
+        }
+    });
+    let message_arms = errors.iter().map(|e| {
+        let name = format_ident!("{}", e.name);
+        let msg = e.msg.as_deref().unwrap_or(&e.name);
+        quote! {
+            Self::#name => #msg
+        }
+    });
+
+    quote! {
+        #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+        #[repr(u32)]
+        pub enum ErrorCode {
+            #(#variants),*
+        }
+
+        impl ErrorCode {
+            /// Looks up the error with the given custom program error code.
+            pub fn from_custom_code(code: u32) -> Option<Self> {
+                match code {
+                    #(#from_code_arms,)*
+                    _ => None,
+                }
+            }
+
+            /// Looks up the error of a [ProgramError::Custom].
+            pub fn from_program_error(error: &ProgramError) -> Option<Self> {
+                match error {
+                    ProgramError::Custom(code) => Self::from_custom_code(*code),
+                    _ => None,
+                }
+            }
+
+            /// Parses the error out of a program log line.
+            ///
+            /// Recognizes both `AnchorError ... Error Number: <code>.` lines and
+            /// `custom program error: 0x<code>` lines.
+            pub fn from_log(log: &str) -> Option<Self> {
+                let code = if log.contains("AnchorError") {
+                    let (_, rest) = log.split_once("Error Number: ")?;
+                    let digits = rest
+                        .find(|c: char| !c.is_ascii_digit())
+                        .map_or(rest, |end| &rest[..end]);
+                    digits.parse().ok()?
+                } else {
+                    let (_, rest) = log.split_once("custom program error: 0x")?;
+                    let digits = rest
+                        .find(|c: char| !c.is_ascii_hexdigit())
+                        .map_or(rest, |end| &rest[..end]);
+                    u32::from_str_radix(digits, 16).ok()?
+                };
+                Self::from_custom_code(code)
+            }
+
+            /// Parses the first error found in the logs of a transaction.
+            pub fn from_logs<S: AsRef<str>>(logs: &[S]) -> Option<Self> {
+                logs.iter().find_map(|log| Self::from_log(log.as_ref()))
+            }
+
+            /// Name of the error.
+            pub fn name(&self) -> &'static str {
+                match self {
+                    #(#name_arms),*
+                }
+            }
+
+            /// Message of the error.
+            pub fn message(&self) -> &'static str {
+                match self {
+                    #(#message_arms),*
+                }
+            }
+        }
+
+        impl TryFrom<u32> for ErrorCode {
+            type Error = u32;
+
+            fn try_from(code: u32) -> std::result::Result<Self, u32> {
+                Self::from_custom_code(code).ok_or(code)
+            }
+        }
+
+        impl From<ErrorCode> for u32 {
+            fn from(error: ErrorCode) -> u32 {
+                error as u32
+            }
+        }
+
+        impl From<ErrorCode> for ProgramError {
+            fn from(error: ErrorCode) -> ProgramError {
+                ProgramError::Custom(error as u32)
+            }
+        }
+
+        impl std::fmt::Display for ErrorCode {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.write_str(self.message())
+            }
+        }
+
+        impl std::error::Error for ErrorCode {}
+    }
+}
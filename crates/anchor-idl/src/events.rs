@@ -0,0 +1,173 @@
+use anchor_syn::idl::types::{Idl, IdlEvent, IdlField, IdlTypeDefinition, IdlTypeDefinitionTy};
+use heck::ToSnakeCase;
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+
+use crate::IdlExtensions;
+
+/// Lists the typedefs which types may refer to, i.e. the types, the accounts and the events of
+/// an IDL.
+///
+/// Events of Anchor 0.30+ IDLs are only generated in the `events` module, so the types
+/// referring to them use the event structs.
+pub fn get_resolvable_typedefs(idl: &Idl) -> Vec<IdlTypeDefinition> {
+    let mut defs: Vec<IdlTypeDefinition> = idl.types.iter().chain(&idl.accounts).cloned().collect();
+    for event in idl.events.iter().flatten() {
+        if defs.iter().all(|def| def.name != event.name) {
+            defs.push(IdlTypeDefinition {
+                name: event.name.clone(),
+                docs: None,
+                generics: None,
+                ty: IdlTypeDefinitionTy::Struct {
+                    fields: event
+                        .fields
+                        .iter()
+                        .map(|field| IdlField {
+                            name: field.name.clone(),
+                            docs: None,
+                            ty: field.ty.clone(),
+                        })
+                        .collect(),
+                },
+            });
+        }
+    }
+    defs
+}
+
+/// Lists the events which are not typedefs but are referred to by types, and so are
+/// re-exported from the root of the generated crate.
+pub fn get_referenced_events(idl: &Idl) -> Vec<&IdlEvent> {
+    let types = crate::get_idl_types(idl);
+    idl.events
+        .iter()
+        .flatten()
+        .filter(|event| {
+            idl.types
+                .iter()
+                .chain(&idl.accounts)
+                .all(|def| def.name != event.name)
+                && types.iter().any(|ty| match ty {
+                    anchor_syn::idl::types::IdlType::Defined(name)
+                    | anchor_syn::idl::types::IdlType::DefinedWithTypeArgs { name, .. } => {
+                        *name == event.name
+                    }
+                    _ => false,
+                })
+        })
+        .collect()
+}
+
+/// Generates an event struct.
+///
+/// Events with a custom discriminator implement the event traits by hand, as `#[event]`
+/// derives the discriminator from the name.
+pub fn generate_event(event: &IdlEvent, extensions: &IdlExtensions) -> TokenStream {
+    let struct_name = format_ident!("{}", event.name);
+    let fields_rendered = event.fields.iter().map(|field| {
+        let name = crate::generate_ident(&field.name.to_snake_case());
+        let stream: TokenStream = crate::ty_to_rust_type(&field.ty).parse().unwrap();
+        let index = if field.index {
+            quote! { #[index] }
+        } else {
+            quote! {}
+        };
+        quote! {
+            #index
+            pub #name: #stream
+        }
+    });
+    let discriminator = extensions.event_discriminator(&event.name);
+    if discriminator == crate::sighash("event", &event.name) {
+        return quote! {
+            #[event]
+            #[derive(Clone, Debug)]
+            pub struct #struct_name {
+                #(#fields_rendered),*
+            }
+        };
+    }
+    quote! {
+        #[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+        pub struct #struct_name {
+            #(#fields_rendered),*
+        }
+
+        #[automatically_derived]
+        impl anchor_lang::Event for #struct_name {
+            fn data(&self) -> Vec<u8> {
+                let mut data = vec![#(#discriminator),*];
+                data.append(&mut self.try_to_vec().unwrap());
+                data
+            }
+        }
+
+        #[automatically_derived]
+        impl anchor_lang::Discriminator for #struct_name {
+            fn discriminator() -> [u8; 8] {
+                [#(#discriminator),*]
+            }
+        }
+    }
+}
+
+/// Generates event structs and the `ProgramEvent` decoder.
+pub fn generate_events(events: &[IdlEvent], extensions: &IdlExtensions) -> TokenStream {
+    let structs = events.iter().map(|event| generate_event(event, extensions));
+    let variants = events.iter().map(|event| {
+        let name = format_ident!("{}", event.name);
+        quote! {
+            #name(#name)
+        }
+    });
+    let decode_arms = events.iter().map(|event| {
+        let name = format_ident!("{}", event.name);
+        let discriminator = extensions.event_discriminator(&event.name);
+        quote! {
+            if let Some(mut data) = data.strip_prefix(&[#(#discriminator),*][..]) {
+                return #name::deserialize(&mut data).ok().map(Self::#name);
+            }
+        }
+    });
+
+    quote! {
+        #(#structs)*
+
+        /// Prefix of the instruction data of the self-CPI performed by `emit_cpi!`.
+        pub const EVENT_IX_TAG: [u8; 8] = [0xe4, 0x45, 0xa5, 0x2e, 0x51, 0xcb, 0x9a, 0x1d];
+
+        /// An event emitted by the program.
+        #[derive(Clone, Debug)]
+        pub enum ProgramEvent {
+            #(#variants),*
+        }
+
+        impl ProgramEvent {
+            /// Decodes an event from its discriminator-prefixed data.
+            pub fn decode(data: &[u8]) -> Option<Self> {
+                #(#decode_arms)*
+                None
+            }
+
+            /// Decodes an event from a `Program data: <base64>` log line.
+            #[allow(deprecated)]
+            pub fn from_log(log: &str) -> Option<Self> {
+                let encoded = log.strip_prefix("Program data: ")?;
+                let data = anchor_lang::__private::base64::decode(encoded.trim()).ok()?;
+                Self::decode(&data)
+            }
+
+            /// Decodes all events found in the logs of a transaction.
+            pub fn from_logs<S: AsRef<str>>(logs: &[S]) -> Vec<Self> {
+                logs.iter()
+                    .filter_map(|log| Self::from_log(log.as_ref()))
+                    .collect()
+            }
+
+            /// Decodes an event from the instruction data of an `emit_cpi!` self-CPI.
+            pub fn from_cpi_data(data: &[u8]) -> Option<Self> {
+                Self::decode(data.strip_prefix(&EVENT_IX_TAG[..])?)
+            }
+        }
+    }
+}
@@ -4,9 +4,8 @@ use proc_macro2::TokenStream;
 use quote::{format_ident, quote};
 use syn::Ident;
 
-use crate::GlamIxCodeGenConfig;
+use crate::{sighash, GlamIxCodeGenConfig};
 use serde::{Deserialize, Serialize};
-use sha2::{Digest, Sha256};
 use std::collections::HashMap;
 
 #[derive(Debug, Default, Serialize, Deserialize, Clone)]
@@ -42,24 +41,9 @@ pub struct GlamIxRemapping {
     pub instructions: Vec<IxInfo>,
 }
 
-fn compute_discriminator(ix_name: &str) -> [u8; 8] {
-    // Format the identifier as per Anchor's convention
-    let identifier = format!("global:{}", ix_name);
-
-    // Compute the SHA-256 hash of the identifier
-    let mut hasher = Sha256::new();
-    hasher.update(identifier.as_bytes());
-    let hash = hasher.finalize();
-
-    // Extract the first 8 bytes as the discriminator
-    let mut discriminator = [0u8; 8];
-    discriminator.copy_from_slice(&hash[..8]);
-    discriminator
-}
-
 /// Generates a single instruction handler.
 pub fn generate_ix_handler(ix: &IdlInstruction) -> TokenStream {
-    let ix_name = format_ident!("{}", ix.name.to_snake_case());
+    let ix_name = crate::generate_handler_ident(&ix.name);
     let accounts_name = format_ident!("{}", ix.name.to_pascal_case());
 
     let args = ix
@@ -75,8 +59,30 @@ pub fn generate_ix_handler(ix: &IdlInstruction) -> TokenStream {
         })
         .collect::<Vec<_>>();
 
+    // Arguments cannot carry doc attributes, so their docs are listed in the handler docs.
+    let mut docs = ix.docs.clone().unwrap_or_default();
+    let arg_docs = ix
+        .args
+        .iter()
+        .filter_map(|arg| {
+            arg.docs
+                .as_ref()
+                .map(|docs| format!("* `{}` - {}", arg.name.to_snake_case(), docs.join(" ")))
+        })
+        .collect::<Vec<_>>();
+    if !arg_docs.is_empty() {
+        if !docs.is_empty() {
+            docs.push(String::new());
+        }
+        docs.push("# Arguments".to_string());
+        docs.push(String::new());
+        docs.extend(arg_docs);
+    }
+    let docs = crate::generate_docs(Some(&docs));
+
     if cfg!(feature = "compat-program-result") {
         quote! {
+            #docs
             pub fn #ix_name(
                 _ctx: Context<#accounts_name>,
                 #(#args),*
@@ -86,6 +92,7 @@ pub fn generate_ix_handler(ix: &IdlInstruction) -> TokenStream {
         }
     } else {
         quote! {
+            #docs
             pub fn #ix_name(
                 _ctx: Context<#accounts_name>,
                 #(#args),*
@@ -96,6 +103,7 @@ pub fn generate_ix_handler(ix: &IdlInstruction) -> TokenStream {
     }
 }
 
+#[allow(clippy::type_complexity)]
 pub fn generate_glam_ix_structs(
     ixs: &[IdlInstruction],
     program_name: &Ident,
@@ -177,12 +185,12 @@ pub fn generate_glam_ix_structs(
                 program_name.to_string().to_snake_case(),
                 ix.name.to_snake_case()
             );
-            let src_discriminator = compute_discriminator(&src_ix_name);
-            let dst_discriminator = compute_discriminator(&dst_ix_name);
+            let src_discriminator = sighash("global", &src_ix_name);
+            let dst_discriminator = sighash("global", &dst_ix_name);
 
             let mut glam_ix_idx = 3;
-            for idx in 0..all_accounts.len() {
-                if !accounts_to_keep.contains(&all_accounts[idx]) {
+            for account in &all_accounts {
+                if !accounts_to_keep.contains(account) {
                     index_map.push(-1);
                 } else {
                     glam_ix_idx += 1;
@@ -213,7 +221,7 @@ pub fn generate_glam_ix_structs(
                         quote! { #[account(constraint = glam_state.enabled @ GlamError::GlamStateDisabled)] }
                     }
                 })
-                .unwrap_or(quote! {});
+                .unwrap_or_default();
 
             let seeds =
                 quote! { [crate::constants::SEED_VAULT.as_bytes(), glam_state.key().as_ref()] };
@@ -324,10 +332,12 @@ pub fn generate_ix_structs(ixs: &[IdlInstruction]) -> TokenStream {
 
         let (all_structs, all_fields) =
             crate::generate_account_fields(&ix.name.to_pascal_case(), &ix.accounts);
+        let docs = crate::generate_docs(ix.docs.as_deref());
 
         quote! {
             #all_structs
 
+            #docs
             #[derive(Accounts)]
             pub struct #accounts_name<'info> {
 
@@ -358,7 +368,7 @@ pub fn generate_glam_ix_handler(
     let program_name_pascal_case = format_ident!("{}", program_name.to_string().to_pascal_case());
 
     let glam_ix_name = format_ident!("{}_{}", program_name_snake_case, ix.name.to_snake_case());
-    let cpi_ix_name = format_ident!("{}", ix.name.to_snake_case());
+    let cpi_ix_name = crate::generate_handler_ident(&ix.name);
 
     let cpi_ix_accounts_name = format_ident!("{}", ix.name.to_pascal_case());
 
@@ -377,7 +387,7 @@ pub fn generate_glam_ix_handler(
         .args
         .iter()
         .map(|arg| {
-            let name = format_ident!("{}", arg.name.to_snake_case());
+            let name = crate::generate_ident(&arg.name.to_snake_case());
             let type_name = crate::ty_to_rust_type(&arg.ty);
             let stream: proc_macro2::TokenStream = type_name.parse().unwrap();
             quote! {
@@ -390,7 +400,7 @@ pub fn generate_glam_ix_handler(
         .args
         .iter()
         .map(|arg| {
-            let name = format_ident!("{}", arg.name.to_snake_case());
+            let name = crate::generate_ident(&arg.name.to_snake_case());
             quote! {
                 #name
             }
@@ -408,7 +418,7 @@ pub fn generate_glam_ix_handler(
                     quote! {}
                 };
 
-            let name = format_ident!("{}", arg.name.to_snake_case());
+            let name = crate::generate_ident(&arg.name.to_snake_case());
             quote! {
                 #use_ref #name
             }
@@ -448,7 +458,7 @@ pub fn generate_glam_ix_handler(
             let account_infos = v
                 .iter()
                 .map(|(account, is_optional)| {
-                    let name = format_ident!("{}", account.to_snake_case());
+                    let name = crate::generate_ident(&account.to_snake_case());
                     if vault_aliases.contains(&account.to_snake_case()) {
                         if *is_optional {
                             quote! {
@@ -496,7 +506,7 @@ pub fn generate_glam_ix_handler(
             let sub_account_infos = v
                 .iter()
                 .map(|(account, is_optional)| {
-                    let name = format_ident!("{}", account.to_snake_case());
+                    let name = crate::generate_ident(&account.to_snake_case());
                     if vault_aliases.contains(&account.to_snake_case()) {
                         if *is_optional {
                             quote! {
@@ -530,7 +540,7 @@ pub fn generate_glam_ix_handler(
                     }
                 })
                 .collect::<Vec<_>>();
-            let sub_account_name = format_ident!("{}", k);
+            let sub_account_name = crate::generate_ident(k);
             let sub_account_struct_name = format_ident!("{}", k.to_snake_case().to_pascal_case());
             quote! {
                 #sub_account_name:  #program_name_snake_case::cpi::accounts::#sub_account_struct_name {
@@ -541,7 +551,7 @@ pub fn generate_glam_ix_handler(
         .collect::<Vec<_>>();
 
     // sort sub_account_infos so that generated code is deterministic
-    sub_account_infos.sort_by(|a, b| a.to_string().cmp(&b.to_string()));
+    sub_account_infos.sort_by_key(|a| a.to_string());
 
     let access_control_permission = if let Some(permission) = &ix_code_gen_config.permission {
         let permission = format_ident!("{}", permission);
@@ -619,6 +629,7 @@ pub fn generate_glam_ix_handler(
     }
 }
 
+#[allow(clippy::type_complexity)]
 pub fn generate_glam_ix_handlers(
     ixs: &[IdlInstruction],
     program_name: &Ident,
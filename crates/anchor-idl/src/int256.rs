@@ -0,0 +1,439 @@
+use std::collections::BTreeMap;
+
+use anchor_syn::idl::types::{EnumFields, Idl, IdlField, IdlType, IdlTypeDefinitionTy};
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+
+use crate::StructOpts;
+
+/// Lists all types referenced by an IDL, including the types nested in them.
+pub fn get_idl_types(idl: &Idl) -> Vec<&IdlType> {
+    fn push_nested<'a>(types: &mut Vec<&'a IdlType>, ty: &'a IdlType) {
+        types.push(ty);
+        match ty {
+            IdlType::Option(inner)
+            | IdlType::Vec(inner)
+            | IdlType::Array(inner, _)
+            | IdlType::GenericLenArray(inner, _) => push_nested(types, inner),
+            IdlType::DefinedWithTypeArgs { args, .. } => {
+                for arg in args {
+                    if let anchor_syn::idl::types::IdlDefinedTypeArg::Type(ty) = arg {
+                        push_nested(types, ty);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut types = vec![];
+    for def in idl.types.iter().chain(&idl.accounts) {
+        match &def.ty {
+            IdlTypeDefinitionTy::Struct { fields } => {
+                fields.iter().for_each(|f| push_nested(&mut types, &f.ty))
+            }
+            IdlTypeDefinitionTy::Enum { variants } => {
+                for variant in variants {
+                    match &variant.fields {
+                        Some(EnumFields::Named(fields)) => {
+                            fields.iter().for_each(|f| push_nested(&mut types, &f.ty))
+                        }
+                        Some(EnumFields::Tuple(fields)) => {
+                            fields.iter().for_each(|ty| push_nested(&mut types, ty))
+                        }
+                        None => {}
+                    }
+                }
+            }
+            IdlTypeDefinitionTy::Alias { value } => push_nested(&mut types, value),
+        }
+    }
+    for ix in &idl.instructions {
+        ix.args.iter().for_each(|f| push_nested(&mut types, &f.ty));
+        if let Some(returns) = &ix.returns {
+            push_nested(&mut types, returns);
+        }
+    }
+    for event in idl.events.iter().flatten() {
+        event
+            .fields
+            .iter()
+            .for_each(|f| push_nested(&mut types, &f.ty));
+    }
+    for constant in &idl.constants {
+        push_nested(&mut types, &constant.ty);
+    }
+    types
+}
+
+/// Converts a decimal integer literal to the little-endian two's complement bytes of a
+/// 256-bit integer.
+pub fn parse_int256(value: &str) -> Option<[u8; 32]> {
+    let value = value.trim().replace('_', "");
+    let (negative, digits) = match value.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, value.as_str()),
+    };
+    if digits.is_empty() {
+        return None;
+    }
+    let mut bytes = [0u8; 32];
+    for digit in digits.chars() {
+        let mut carry = digit.to_digit(10)?;
+        for byte in bytes.iter_mut() {
+            let product = *byte as u32 * 10 + carry;
+            *byte = product as u8;
+            carry = product >> 8;
+        }
+        if carry != 0 {
+            return None;
+        }
+    }
+    if negative {
+        let mut carry = 1;
+        for byte in bytes.iter_mut() {
+            let sum = !*byte as u32 + carry;
+            *byte = sum as u8;
+            carry = sum >> 8;
+        }
+    }
+    Some(bytes)
+}
+
+/// Generates a 256-bit integer type.
+///
+/// The type wraps the little-endian bytes of the integer, so it has the Borsh layout of the
+/// integer and an alignment of 1, which makes it usable in zero copy and packed structs.
+pub fn generate_int256(signed: bool) -> TokenStream {
+    let name = format_ident!("{}", if signed { "I256" } else { "U256" });
+    let doc = if signed {
+        " A signed 256-bit integer, stored as little-endian two's complement bytes."
+    } else {
+        " An unsigned 256-bit integer, stored as little-endian bytes."
+    };
+    let (half, from_types, sign_extension, narrow) = if signed {
+        (
+            quote! { i128 },
+            ["i8", "i16", "i32", "i64"],
+            quote! { if value < 0 { 0xff } else { 0 } },
+            // The value fits if its upper half is the sign extension of its lower half.
+            quote! {
+                let low = low as i128;
+                if high == low >> 127 {
+                    Ok(low)
+                } else {
+                    Err(value)
+                }
+            },
+        )
+    } else {
+        (
+            quote! { u128 },
+            ["u8", "u16", "u32", "u64"],
+            quote! { 0 },
+            quote! {
+                if high == 0 {
+                    Ok(low)
+                } else {
+                    Err(value)
+                }
+            },
+        )
+    };
+
+    let from_types = from_types.iter().map(|ty| format_ident!("{}", ty));
+
+    quote! {
+        #[doc = #doc]
+        #[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+        #[repr(transparent)]
+        pub struct #name(pub [u8; 32]);
+
+        unsafe impl anchor_lang::__private::bytemuck::Zeroable for #name {}
+        unsafe impl anchor_lang::__private::bytemuck::Pod for #name {}
+
+        impl #name {
+            pub const ZERO: Self = Self([0; 32]);
+
+            pub const fn from_le_bytes(bytes: [u8; 32]) -> Self {
+                Self(bytes)
+            }
+
+            pub const fn to_le_bytes(self) -> [u8; 32] {
+                self.0
+            }
+
+            /// Builds the integer from its lower and upper 128 bits.
+            pub fn from_halves(low: u128, high: #half) -> Self {
+                let mut bytes = [0; 32];
+                bytes[..16].copy_from_slice(&low.to_le_bytes());
+                bytes[16..].copy_from_slice(&high.to_le_bytes());
+                Self(bytes)
+            }
+
+            /// Splits the integer into its lower and upper 128 bits.
+            pub fn to_halves(self) -> (u128, #half) {
+                let mut low = [0; 16];
+                let mut high = [0; 16];
+                low.copy_from_slice(&self.0[..16]);
+                high.copy_from_slice(&self.0[16..]);
+                (u128::from_le_bytes(low), #half::from_le_bytes(high))
+            }
+        }
+
+        impl Ord for #name {
+            fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+                let (low, high) = self.to_halves();
+                let (other_low, other_high) = other.to_halves();
+                (high, low).cmp(&(other_high, other_low))
+            }
+        }
+
+        impl PartialOrd for #name {
+            fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+                Some(self.cmp(other))
+            }
+        }
+
+        impl From<#half> for #name {
+            fn from(value: #half) -> Self {
+                let mut bytes = [#sign_extension; 32];
+                bytes[..16].copy_from_slice(&value.to_le_bytes());
+                Self(bytes)
+            }
+        }
+
+        impl TryFrom<#name> for #half {
+            type Error = #name;
+
+            /// Fails if the value does not fit in 128 bits.
+            fn try_from(value: #name) -> std::result::Result<Self, #name> {
+                let (low, high) = value.to_halves();
+                #narrow
+            }
+        }
+
+        #(
+            impl From<#from_types> for #name {
+                fn from(value: #from_types) -> Self {
+                    Self::from(value as #half)
+                }
+            }
+        )*
+    }
+}
+
+/// Generates the 256-bit integer types used by an IDL.
+pub fn generate_int256_types(idl: &Idl) -> TokenStream {
+    let types = get_idl_types(idl);
+    let unsigned = if types.iter().any(|ty| **ty == IdlType::U256) {
+        generate_int256(false)
+    } else {
+        quote! {}
+    };
+    let signed = if types.iter().any(|ty| **ty == IdlType::I256) {
+        generate_int256(true)
+    } else {
+        quote! {}
+    };
+    quote! {
+        #unsigned
+        #signed
+    }
+}
+
+/// Generates an alignment-safe 128-bit integer type for zero copy structs.
+///
+/// The type stores the lower and upper 64 bits of the integer, so it has the size and the
+/// alignment of 8 of the integer on Solana on every target, while hosts align `u128` to 16.
+pub fn generate_pod_int128(signed: bool) -> TokenStream {
+    let name = format_ident!("{}", if signed { "PodI128" } else { "PodU128" });
+    let int = format_ident!("{}", if signed { "i128" } else { "u128" });
+    let doc = format!(
+        " An `{}` with the layout it has on Solana, for use in zero copy structs.",
+        int
+    );
+
+    quote! {
+        #[doc = #doc]
+        #[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, PartialEq, Eq, Hash)]
+        #[repr(C)]
+        pub struct #name([u64; 2]);
+
+        unsafe impl anchor_lang::__private::bytemuck::Zeroable for #name {}
+        unsafe impl anchor_lang::__private::bytemuck::Pod for #name {}
+
+        impl #name {
+            pub const ZERO: Self = Self([0; 2]);
+
+            pub const fn new(value: #int) -> Self {
+                Self([value as u64, (value >> 64) as u64])
+            }
+
+            pub const fn get(self) -> #int {
+                self.0[0] as #int | (self.0[1] as #int) << 64
+            }
+        }
+
+        impl std::fmt::Debug for #name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                std::fmt::Debug::fmt(&self.get(), f)
+            }
+        }
+
+        impl std::fmt::Display for #name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                std::fmt::Display::fmt(&self.get(), f)
+            }
+        }
+
+        impl Ord for #name {
+            fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+                self.get().cmp(&other.get())
+            }
+        }
+
+        impl PartialOrd for #name {
+            fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+                Some(self.cmp(other))
+            }
+        }
+
+        impl From<#int> for #name {
+            fn from(value: #int) -> Self {
+                Self::new(value)
+            }
+        }
+
+        impl From<#name> for #int {
+            fn from(value: #name) -> Self {
+                value.get()
+            }
+        }
+    }
+}
+
+/// Replaces the `u128` and `i128` types of `ty` with their alignment-safe wrappers.
+fn wrap_int128(ty: &IdlType) -> IdlType {
+    match ty {
+        IdlType::U128 => IdlType::Defined("PodU128".to_string()),
+        IdlType::I128 => IdlType::Defined("PodI128".to_string()),
+        IdlType::Array(inner, len) => IdlType::Array(Box::new(wrap_int128(inner)), *len),
+        _ => ty.clone(),
+    }
+}
+
+/// Lists the fields of a struct as generated, with their `u128` and `i128` types replaced
+/// with alignment-safe wrappers if `opts.aligned_int128`.
+pub fn get_generated_fields(fields: &[IdlField], opts: StructOpts) -> Vec<IdlField> {
+    fields
+        .iter()
+        .map(|field| IdlField {
+            ty: if opts.zero_copy && opts.aligned_int128 {
+                wrap_int128(&field.ty)
+            } else {
+                field.ty.clone()
+            },
+            ..field.clone()
+        })
+        .collect()
+}
+
+/// Generates the alignment-safe 128-bit integer types used by the zero copy structs of an
+/// IDL.
+pub fn generate_pod_int128_types(
+    idl: &Idl,
+    struct_opts: &BTreeMap<String, StructOpts>,
+) -> TokenStream {
+    let types: Vec<IdlType> = idl
+        .types
+        .iter()
+        .chain(&idl.accounts)
+        .filter_map(|def| match &def.ty {
+            IdlTypeDefinitionTy::Struct { fields } => {
+                let opts = struct_opts.get(&def.name).copied().unwrap_or_default();
+                Some(get_generated_fields(fields, opts))
+            }
+            _ => None,
+        })
+        .flatten()
+        .map(|field| field.ty)
+        .collect();
+    fn holds(ty: &IdlType, wrapper: &IdlType) -> bool {
+        match ty {
+            IdlType::Array(inner, _) => holds(inner, wrapper),
+            ty => ty == wrapper,
+        }
+    }
+    let uses = |name: &str| {
+        let wrapper = IdlType::Defined(name.to_string());
+        types.iter().any(|ty| holds(ty, &wrapper))
+    };
+    let unsigned = if uses("PodU128") {
+        generate_pod_int128(false)
+    } else {
+        quote! {}
+    };
+    let signed = if uses("PodI128") {
+        generate_pod_int128(true)
+    } else {
+        quote! {}
+    };
+    quote! {
+        #unsigned
+        #signed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn from_u128(value: u128) -> [u8; 32] {
+        let mut bytes = [0u8; 32];
+        bytes[..16].copy_from_slice(&value.to_le_bytes());
+        bytes
+    }
+
+    #[test]
+    fn parse_int256_small_values() {
+        assert_eq!(parse_int256("0"), Some([0; 32]));
+        assert_eq!(parse_int256(" 1_000 "), Some(from_u128(1000)));
+        assert_eq!(
+            parse_int256(&u128::MAX.to_string()),
+            Some(from_u128(u128::MAX))
+        );
+    }
+
+    #[test]
+    fn parse_int256_negative_values() {
+        assert_eq!(parse_int256("-1"), Some([0xff; 32]));
+        let mut expected = [0xff; 32];
+        expected[0] = 0xfe;
+        assert_eq!(parse_int256("-2"), Some(expected));
+        assert_eq!(parse_int256("-0"), Some([0; 32]));
+    }
+
+    #[test]
+    fn parse_int256_bounds() {
+        // 2^256 - 1
+        let max = "115792089237316195423570985008687907853269984665640564039457584007913129639935";
+        assert_eq!(parse_int256(max), Some([0xff; 32]));
+        // 2^256
+        let overflow =
+            "115792089237316195423570985008687907853269984665640564039457584007913129639936";
+        assert_eq!(parse_int256(overflow), None);
+        // -2^255
+        let min = "-57896044618658097711785492504343953926634992332820282019728792003956564819968";
+        let mut expected = [0; 32];
+        expected[31] = 0x80;
+        assert_eq!(parse_int256(min), Some(expected));
+    }
+
+    #[test]
+    fn parse_int256_rejects_invalid_literals() {
+        for value in ["", "-", "0x10", "1e3", "12a", "--1", "1.5"] {
+            assert_eq!(parse_int256(value), None, "{:?}", value);
+        }
+    }
+}
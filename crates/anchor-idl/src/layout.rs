@@ -0,0 +1,490 @@
+use std::collections::BTreeMap;
+
+use anchor_syn::idl::types::{IdlField, IdlType, IdlTypeDefinition, IdlTypeDefinitionTy};
+use heck::ToSnakeCase;
+use proc_macro2::{Literal, TokenStream};
+use quote::{format_ident, quote, ToTokens};
+
+use crate::StructOpts;
+
+/// Alignment of `u128` and `i128` on Solana.
+const SOLANA_WIDE_ALIGN: usize = 8;
+
+/// Alignment of `u128` and `i128` on most hosts since Rust 1.77.
+const HOST_WIDE_ALIGN: usize = 16;
+
+/// Size and alignment of a type held by a zero copy struct.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Layout {
+    pub size: usize,
+    pub align: usize,
+}
+
+/// Layout of a zero copy struct, along with the offsets of its fields.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StructLayout {
+    pub layout: Layout,
+    pub offsets: Vec<usize>,
+}
+
+/// Computes the layouts of zero copy structs from the IDL.
+struct LayoutCalculator<'a> {
+    defs: &'a [IdlTypeDefinition],
+    struct_opts: &'a BTreeMap<String, StructOpts>,
+    /// Alignment of `u128` and `i128`, which depends on the target.
+    wide_align: usize,
+}
+
+impl LayoutCalculator<'_> {
+    /// Computes the layout of a type, or `None` if it is not known from the IDL, e.g. for
+    /// external types.
+    fn type_layout(&self, ty: &IdlType) -> Option<Layout> {
+        let primitive = |size| Some(Layout { size, align: size });
+        match ty {
+            IdlType::Bool | IdlType::U8 | IdlType::I8 => primitive(1),
+            IdlType::U16 | IdlType::I16 => primitive(2),
+            IdlType::U32 | IdlType::I32 | IdlType::F32 => primitive(4),
+            IdlType::U64 | IdlType::I64 | IdlType::F64 => primitive(8),
+            IdlType::U128 | IdlType::I128 => Some(Layout {
+                size: 16,
+                align: self.wide_align,
+            }),
+            // Both are byte arrays.
+            IdlType::U256 | IdlType::I256 | IdlType::PublicKey => {
+                Some(Layout { size: 32, align: 1 })
+            }
+            IdlType::Array(inner, len) => {
+                let inner = self.type_layout(inner)?;
+                Some(Layout {
+                    size: inner.size * len,
+                    align: inner.align,
+                })
+            }
+            IdlType::Defined(name) => {
+                let def = self.defs.iter().find(|def| def.name == *name)?;
+                match &def.ty {
+                    IdlTypeDefinitionTy::Struct { fields } => {
+                        let opts = self.struct_opts.get(name).filter(|opts| opts.zero_copy)?;
+                        Some(self.struct_layout(fields, *opts)?.layout)
+                    }
+                    IdlTypeDefinitionTy::Alias { value } => self.type_layout(value),
+                    IdlTypeDefinitionTy::Enum { .. } => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Computes the layout of a `repr(C)` struct, or of a `repr(C, packed)` one if `packed`.
+    fn struct_layout(&self, fields: &[IdlField], opts: StructOpts) -> Option<StructLayout> {
+        let mut offsets = Vec::with_capacity(fields.len());
+        let mut size: usize = 0;
+        let mut align = opts.align.unwrap_or(1);
+        for field in fields {
+            let field = self.type_layout(&field.ty)?;
+            let field_align = if opts.packed { 1 } else { field.align };
+            size = size.next_multiple_of(field_align);
+            offsets.push(size);
+            size += field.size;
+            align = align.max(field_align);
+        }
+        Some(StructLayout {
+            layout: Layout {
+                size: size.next_multiple_of(align),
+                align,
+            },
+            offsets,
+        })
+    }
+}
+
+/// Finds a type which cannot be held by a zero copy struct, i.e. one which is not plain old
+/// data, and describes why.
+///
+/// Structs held by zero copy structs must be zero copy themselves, as the layout of other
+/// structs is unspecified.
+pub fn find_non_pod(
+    defs: &[IdlTypeDefinition],
+    struct_opts: &BTreeMap<String, StructOpts>,
+    ty: &IdlType,
+    visited: &mut Vec<String>,
+) -> Option<String> {
+    match ty {
+        IdlType::Vec(_) | IdlType::Bytes | IdlType::String | IdlType::Option(_) => Some(format!(
+            "`{}`, which is not plain old data",
+            crate::ty_to_rust_type(ty)
+        )),
+        IdlType::Array(inner, _) | IdlType::GenericLenArray(inner, _) => {
+            find_non_pod(defs, struct_opts, inner, visited)
+        }
+        IdlType::Defined(name) | IdlType::DefinedWithTypeArgs { name, .. } => {
+            if visited.contains(name) {
+                return None;
+            }
+            visited.push(name.clone());
+            let def = defs.iter().find(|def| def.name == *name)?;
+            let types: Vec<&IdlType> = match &def.ty {
+                IdlTypeDefinitionTy::Struct { .. }
+                    if !struct_opts.get(name).is_some_and(|opts| opts.zero_copy) =>
+                {
+                    return Some(format!("`{}`, which is not zero copy", name));
+                }
+                IdlTypeDefinitionTy::Struct { fields } => {
+                    fields.iter().map(|field| &field.ty).collect()
+                }
+                IdlTypeDefinitionTy::Alias { value } => vec![value],
+                IdlTypeDefinitionTy::Enum { .. } => {
+                    return Some(format!("`{}`, which is an enum", name));
+                }
+            };
+            types
+                .into_iter()
+                .find_map(|ty| find_non_pod(defs, struct_opts, ty, visited))
+        }
+        _ => None,
+    }
+}
+
+/// Computes the layout of the zero copy struct `def` on Solana, or `None` if it is not a
+/// zero copy struct or holds types whose layout is not known from the IDL.
+pub fn get_struct_layout(
+    defs: &[IdlTypeDefinition],
+    struct_opts: &BTreeMap<String, StructOpts>,
+    def: &IdlTypeDefinition,
+) -> Option<StructLayout> {
+    get_struct_layout_with_wide_align(defs, struct_opts, def, SOLANA_WIDE_ALIGN)
+}
+
+fn get_struct_layout_with_wide_align(
+    defs: &[IdlTypeDefinition],
+    struct_opts: &BTreeMap<String, StructOpts>,
+    def: &IdlTypeDefinition,
+    wide_align: usize,
+) -> Option<StructLayout> {
+    let IdlTypeDefinitionTy::Struct { fields } = &def.ty else {
+        return None;
+    };
+    if def.generics.as_ref().is_some_and(|g| !g.is_empty()) {
+        return None;
+    }
+    let opts = struct_opts.get(&def.name).filter(|opts| opts.zero_copy)?;
+    LayoutCalculator {
+        defs,
+        struct_opts,
+        // The wrappers of `u128` and `i128` are aligned like them on Solana.
+        wide_align: if opts.aligned_int128 {
+            SOLANA_WIDE_ALIGN
+        } else {
+            wide_align
+        },
+    }
+    .struct_layout(fields, *opts)
+}
+
+/// Generates compile time assertions that the size and field offsets of the zero copy
+/// struct `def` match the layout computed from the IDL.
+///
+/// Structs whose layout depends on the alignment of `u128` are checked against the layout
+/// on Solana on targets aligning `u128` like Solana, and against the layout on most hosts on
+/// targets aligning it to 16 bytes, so that their offsets are checked on both. Targets with
+/// another alignment of `u128` skip the assertions. This does not apply to structs whose
+/// `u128` and `i128` fields are generated as alignment-safe wrappers.
+pub fn generate_layout_assertions(
+    defs: &[IdlTypeDefinition],
+    struct_opts: &BTreeMap<String, StructOpts>,
+    def: &IdlTypeDefinition,
+) -> TokenStream {
+    let (Some(layout), IdlTypeDefinitionTy::Struct { fields }) =
+        (get_struct_layout(defs, struct_opts, def), &def.ty)
+    else {
+        return quote! {};
+    };
+    let host_layout = get_struct_layout_with_wide_align(defs, struct_opts, def, HOST_WIDE_ALIGN);
+
+    let name = format_ident!("{}", def.name);
+    let size_message = format!(
+        "size of zero copy struct `{}` does not match the IDL",
+        def.name
+    );
+    let tuple = crate::is_tuple_struct(fields);
+    let assertions = |layout: &StructLayout| {
+        let size = Literal::usize_unsuffixed(layout.layout.size);
+        let offsets =
+            fields
+                .iter()
+                .zip(&layout.offsets)
+                .enumerate()
+                .map(|(index, (field, offset))| {
+                    let field_name = if tuple {
+                        Literal::usize_unsuffixed(index).to_token_stream()
+                    } else {
+                        crate::generate_ident(&field.name.to_snake_case()).to_token_stream()
+                    };
+                    let offset = Literal::usize_unsuffixed(*offset);
+                    let message = format!(
+                        "offset of `{}.{}` does not match the IDL",
+                        def.name, field.name
+                    );
+                    // `offset_of!` requires Rust 1.77, which older Solana platform tools predate.
+                    quote! {
+                        assert!(
+                            unsafe {
+                                (::core::ptr::addr_of!((*base).#field_name) as *const u8)
+                                    .offset_from(base as *const u8)
+                            } as usize
+                                == #offset,
+                            #message
+                        );
+                    }
+                });
+        let offsets = if fields.is_empty() {
+            quote! {}
+        } else {
+            quote! {
+                let uninit = ::core::mem::MaybeUninit::<#name>::uninit();
+                let base = uninit.as_ptr();
+                #(#offsets)*
+            }
+        };
+        quote! {
+            assert!(::core::mem::size_of::<#name>() == #size, #size_message);
+            #offsets
+        }
+    };
+    match host_layout {
+        Some(host_layout) if host_layout != layout => {
+            let solana_assertions = assertions(&layout);
+            let host_assertions = assertions(&host_layout);
+            let solana_align = Literal::usize_unsuffixed(SOLANA_WIDE_ALIGN);
+            let host_align = Literal::usize_unsuffixed(HOST_WIDE_ALIGN);
+            quote! {
+                const _: () = {
+                    if ::core::mem::align_of::<u128>() == #solana_align {
+                        #solana_assertions
+                    } else if ::core::mem::align_of::<u128>() == #host_align {
+                        #host_assertions
+                    }
+                };
+            }
+        }
+        _ => {
+            let assertions = assertions(&layout);
+            quote! {
+                const _: () = {
+                    #assertions
+                };
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn field(name: &str, ty: IdlType) -> IdlField {
+        IdlField {
+            name: name.to_string(),
+            docs: None,
+            ty,
+        }
+    }
+
+    fn struct_def(name: &str, fields: Vec<IdlField>) -> IdlTypeDefinition {
+        IdlTypeDefinition {
+            name: name.to_string(),
+            docs: None,
+            generics: None,
+            ty: IdlTypeDefinitionTy::Struct { fields },
+        }
+    }
+
+    fn zero_copy(packed: bool, align: Option<usize>) -> StructOpts {
+        StructOpts {
+            zero_copy: true,
+            packed,
+            align,
+            ..Default::default()
+        }
+    }
+
+    fn layout(
+        defs: &[IdlTypeDefinition],
+        opts: &[(&str, StructOpts)],
+        name: &str,
+    ) -> Option<StructLayout> {
+        let struct_opts = opts
+            .iter()
+            .map(|(name, opts)| (name.to_string(), *opts))
+            .collect();
+        let def = defs.iter().find(|def| def.name == name).unwrap();
+        get_struct_layout(defs, &struct_opts, def)
+    }
+
+    fn padded() -> IdlTypeDefinition {
+        struct_def(
+            "Padded",
+            vec![
+                field("a", IdlType::U8),
+                field("b", IdlType::U64),
+                field("c", IdlType::U16),
+            ],
+        )
+    }
+
+    #[test]
+    fn repr_c_layout() {
+        let layout = layout(&[padded()], &[("Padded", zero_copy(false, None))], "Padded");
+        assert_eq!(
+            layout,
+            Some(StructLayout {
+                layout: Layout { size: 24, align: 8 },
+                offsets: vec![0, 8, 16],
+            })
+        );
+    }
+
+    #[test]
+    fn packed_layout() {
+        let layout = layout(&[padded()], &[("Padded", zero_copy(true, None))], "Padded");
+        assert_eq!(
+            layout,
+            Some(StructLayout {
+                layout: Layout { size: 11, align: 1 },
+                offsets: vec![0, 1, 9],
+            })
+        );
+    }
+
+    #[test]
+    fn aligned_layout() {
+        let layout = layout(
+            &[padded()],
+            &[("Padded", zero_copy(false, Some(32)))],
+            "Padded",
+        );
+        assert_eq!(
+            layout.unwrap().layout,
+            Layout {
+                size: 32,
+                align: 32
+            }
+        );
+    }
+
+    #[test]
+    fn nested_layout() {
+        let defs = [
+            struct_def(
+                "Outer",
+                vec![
+                    field("flag", IdlType::Bool),
+                    field(
+                        "inner",
+                        IdlType::Array(Box::new(IdlType::Defined("Inner".to_string())), 2),
+                    ),
+                    field("key", IdlType::PublicKey),
+                ],
+            ),
+            struct_def(
+                "Inner",
+                vec![field("x", IdlType::U32), field("y", IdlType::U8)],
+            ),
+        ];
+        let opts = [
+            ("Outer", zero_copy(false, None)),
+            ("Inner", zero_copy(false, None)),
+        ];
+        assert_eq!(
+            layout(&defs, &opts, "Outer"),
+            Some(StructLayout {
+                layout: Layout { size: 52, align: 4 },
+                offsets: vec![0, 4, 20],
+            })
+        );
+        // Structs which are not zero copy have no known layout.
+        assert_eq!(layout(&defs, &opts[..1], "Outer"), None);
+        assert_eq!(layout(&defs, &opts[1..], "Outer"), None);
+    }
+
+    #[test]
+    fn wide_integer_layout() {
+        let def = struct_def(
+            "Wide",
+            vec![field("a", IdlType::U8), field("b", IdlType::I128)],
+        );
+        let mut struct_opts = BTreeMap::new();
+        struct_opts.insert("Wide".to_string(), zero_copy(false, None));
+        let defs = [def.clone()];
+        let solana = get_struct_layout(&defs, &struct_opts, &def).unwrap();
+        assert_eq!(solana.offsets, [0, 8]);
+        assert_eq!(solana.layout, Layout { size: 24, align: 8 });
+        let host =
+            get_struct_layout_with_wide_align(&defs, &struct_opts, &def, HOST_WIDE_ALIGN).unwrap();
+        assert_eq!(host.offsets, [0, 16]);
+        assert_eq!(
+            host.layout,
+            Layout {
+                size: 32,
+                align: 16
+            }
+        );
+
+        // The wrappers are aligned like on Solana on every target.
+        struct_opts.get_mut("Wide").unwrap().aligned_int128 = true;
+        let wrapped =
+            get_struct_layout_with_wide_align(&defs, &struct_opts, &def, HOST_WIDE_ALIGN).unwrap();
+        assert_eq!(wrapped, solana);
+    }
+
+    #[test]
+    fn unknown_layouts() {
+        let defs = [struct_def(
+            "Holder",
+            vec![field("value", IdlType::Vec(Box::new(IdlType::U8)))],
+        )];
+        assert_eq!(
+            layout(&defs, &[("Holder", zero_copy(false, None))], "Holder"),
+            None
+        );
+    }
+
+    #[test]
+    fn find_non_pod_fields() {
+        let defs = [
+            struct_def("Plain", vec![field("x", IdlType::U64)]),
+            IdlTypeDefinition {
+                name: "Side".to_string(),
+                docs: None,
+                generics: None,
+                ty: IdlTypeDefinitionTy::Enum { variants: vec![] },
+            },
+        ];
+        let mut struct_opts = BTreeMap::new();
+        let non_pod = |ty: IdlType, struct_opts: &BTreeMap<String, StructOpts>| {
+            find_non_pod(&defs, struct_opts, &ty, &mut vec![])
+        };
+        assert_eq!(
+            non_pod(IdlType::String, &struct_opts).as_deref(),
+            Some("`String`, which is not plain old data")
+        );
+        assert_eq!(
+            non_pod(IdlType::Defined("Plain".to_string()), &struct_opts).as_deref(),
+            Some("`Plain`, which is not zero copy")
+        );
+        assert_eq!(
+            non_pod(
+                IdlType::Array(Box::new(IdlType::Defined("Side".to_string())), 2),
+                &struct_opts
+            )
+            .as_deref(),
+            Some("`Side`, which is an enum")
+        );
+        struct_opts.insert("Plain".to_string(), zero_copy(false, None));
+        assert_eq!(
+            non_pod(IdlType::Defined("Plain".to_string()), &struct_opts),
+            None
+        );
+        assert_eq!(non_pod(IdlType::U128, &struct_opts), None);
+    }
+}
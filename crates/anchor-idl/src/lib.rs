@@ -1,23 +1,114 @@
 //! Generates Rust code from an Anchor IDL.
 
-use anchor_syn::idl::types::IdlType;
+use anchor_syn::idl::types::{IdlDefinedTypeArg, IdlType};
 pub use anchor_syn::idl::*;
+use heck::ToSnakeCase;
+use proc_macro2::{Ident, Span, TokenStream};
+use quote::{format_ident, quote};
 
 mod account;
+mod client;
+mod constants;
+mod decoder;
+mod diagnostics;
+mod errors;
+mod events;
 mod instruction;
+mod int256;
+mod layout;
+mod lint;
+mod pda;
 mod program;
+mod spec;
 mod state;
 mod typedef;
 
 pub use account::*;
+pub use client::*;
+pub use constants::*;
+pub use decoder::*;
+pub use diagnostics::*;
+pub use errors::*;
+pub use events::*;
 pub use instruction::*;
+pub use int256::*;
+pub use layout::*;
+pub use lint::*;
+pub use pda::*;
 pub use program::*;
+pub use spec::*;
 pub use state::*;
 pub use typedef::*;
 
 /// Version of anchor-idl.
 pub const GEN_VERSION: Option<&str> = option_env!("CARGO_PKG_VERSION");
 
+/// Generates `#[doc]` attributes from the docs of an IDL item.
+pub fn generate_docs(docs: Option<&[String]>) -> TokenStream {
+    let lines = docs.unwrap_or_default().iter().map(|line| {
+        let line = format!(" {}", line);
+        quote! {
+            #[doc = #line]
+        }
+    });
+    quote! {
+        #(#lines)*
+    }
+}
+
+/// Keywords which cannot be used as identifiers, as of the 2021 edition.
+const RUST_KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "crate",
+    "do", "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "if", "impl", "in",
+    "let", "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref",
+    "return", "self", "Self", "static", "struct", "super", "trait", "true", "try", "type",
+    "typeof", "unsafe", "unsized", "use", "virtual", "where", "while", "yield",
+];
+
+/// Checks whether a name is a Rust keyword.
+pub fn is_rust_keyword(name: &str) -> bool {
+    RUST_KEYWORDS.contains(&name)
+}
+
+/// Keywords which cannot be raw identifiers.
+const NON_RAW_KEYWORDS: &[&str] = &["crate", "self", "Self", "super"];
+
+/// Converts an IDL name to an identifier.
+///
+/// Rust keywords are escaped as raw identifiers (e.g. `r#type`), or suffixed with `_` for
+/// the few keywords which cannot be raw.
+pub fn generate_ident(name: &str) -> Ident {
+    if NON_RAW_KEYWORDS.contains(&name) {
+        format_ident!("{}_", name)
+    } else if is_rust_keyword(name) {
+        Ident::new_raw(name, Span::call_site())
+    } else {
+        format_ident!("{}", name)
+    }
+}
+
+/// Converts the name of an instruction to the identifier of its handler.
+///
+/// Anchor derives the names of the instruction structs of a program from its handlers and
+/// does not support raw identifiers there, so keywords are always suffixed with `_`.
+pub fn generate_handler_ident(ix_name: &str) -> Ident {
+    let name = ix_name.to_snake_case();
+    if is_rust_keyword(&name) {
+        format_ident!("{}_", name)
+    } else {
+        format_ident!("{}", name)
+    }
+}
+
+/// Generates a `Pubkey` expression from a base58 address.
+pub fn generate_pubkey(address: &str) -> Option<TokenStream> {
+    let bytes = bs58::decode(address).into_vec().ok()?;
+    if bytes.len() != 32 {
+        return None;
+    }
+    Some(quote! { Pubkey::new_from_array([#(#bytes),*]) })
+}
+
 /// Converts an [IdlType] to a [String] of the Rust representation.
 pub fn ty_to_rust_type(ty: &IdlType) -> String {
     match ty {
@@ -34,13 +125,35 @@ pub fn ty_to_rust_type(ty: &IdlType) -> String {
         IdlType::F64 => "f64".to_string(),
         IdlType::U128 => "u128".to_string(),
         IdlType::I128 => "i128".to_string(),
+        IdlType::U256 => "U256".to_string(),
+        IdlType::I256 => "I256".to_string(),
         IdlType::Bytes => "Vec<u8>".to_string(),
         IdlType::String => "String".to_string(),
         IdlType::PublicKey => "Pubkey".to_string(),
         IdlType::Option(inner) => format!("Option<{}>", ty_to_rust_type(inner)),
         IdlType::Vec(inner) => format!("Vec<{}>", ty_to_rust_type(inner)),
         IdlType::Array(ty, size) => format!("[{}; {}]", ty_to_rust_type(ty), size),
+        IdlType::GenericLenArray(ty, len) => format!("[{}; {}]", ty_to_rust_type(ty), len),
         IdlType::Defined(name) => name.to_string(),
-        _ => todo!(),
+        IdlType::Generic(name) => name.to_string(),
+        IdlType::DefinedWithTypeArgs { name, args } => format!(
+            "{}<{}>",
+            name,
+            args.iter()
+                .map(type_arg_to_rust_type)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    }
+}
+
+/// Converts an [IdlDefinedTypeArg] to a [String] of the Rust representation.
+fn type_arg_to_rust_type(arg: &IdlDefinedTypeArg) -> String {
+    match arg {
+        IdlDefinedTypeArg::Generic(name) => name.to_string(),
+        // Const arguments other than literals must be wrapped in braces.
+        IdlDefinedTypeArg::Value(value) if value.parse::<u128>().is_ok() => value.to_string(),
+        IdlDefinedTypeArg::Value(value) => format!("{{ {} }}", value),
+        IdlDefinedTypeArg::Type(ty) => ty_to_rust_type(ty),
     }
 }
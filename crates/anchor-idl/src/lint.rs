@@ -0,0 +1,526 @@
+use std::{collections::BTreeMap, fmt};
+
+use anchor_syn::idl::types::{
+    EnumFields, Idl, IdlField, IdlType, IdlTypeDefinition, IdlTypeDefinitionTy,
+};
+use heck::{ToPascalCase, ToSnakeCase};
+use proc_macro2::{Span, TokenStream};
+use quote::{format_ident, quote, quote_spanned};
+
+use crate::StructOpts;
+
+/// Severity of a [LintFinding].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// The generated code is likely not what was intended.
+    Warning,
+    /// The generated code would not compile.
+    Error,
+}
+
+/// An issue found in an IDL by [lint].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LintFinding {
+    pub severity: Severity,
+    /// Path of the offending item in the IDL, e.g. `types.Pool.fields.config`.
+    pub location: String,
+    pub message: String,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+impl fmt::Display for LintFinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (at `{}`)", self.message, self.location)
+    }
+}
+
+struct Linter<'a> {
+    defs: Vec<&'a IdlTypeDefinition>,
+    findings: Vec<LintFinding>,
+}
+
+impl<'a> Linter<'a> {
+    fn push(&mut self, severity: Severity, location: &str, message: String) {
+        self.findings.push(LintFinding {
+            severity,
+            location: location.to_string(),
+            message,
+        });
+    }
+
+    fn find_def(&self, name: &str) -> Option<&'a IdlTypeDefinition> {
+        self.defs.iter().find(|def| def.name == name).copied()
+    }
+
+    /// Reports duplicate names, and distinct names which are converted to the same
+    /// identifier by `convert`.
+    fn check_names<'n>(
+        &mut self,
+        kind: &str,
+        location: &str,
+        names: impl IntoIterator<Item = &'n str>,
+        convert: fn(&str) -> String,
+    ) {
+        let mut seen: BTreeMap<String, &str> = BTreeMap::new();
+        for name in names {
+            match seen.get(&convert(name)) {
+                Some(other) if *other == name => self.push(
+                    Severity::Error,
+                    &format!("{}.{}", location, name),
+                    format!("duplicate {} `{}`", kind, name),
+                ),
+                Some(other) => self.push(
+                    Severity::Error,
+                    &format!("{}.{}", location, name),
+                    format!(
+                        "{}s `{}` and `{}` both generate `{}`",
+                        kind,
+                        other,
+                        name,
+                        convert(name)
+                    ),
+                ),
+                None => {
+                    seen.insert(convert(name), name);
+                }
+            }
+        }
+    }
+
+    /// Checks the names of fields or arguments, which are converted to snake case.
+    ///
+    /// Names which are Rust keywords are escaped, which makes them awkward to use.
+    fn check_field_names<'n>(
+        &mut self,
+        kind: &str,
+        location: &str,
+        names: impl IntoIterator<Item = &'n str>,
+    ) {
+        let names: Vec<&str> = names.into_iter().collect();
+        self.check_names(kind, location, names.iter().copied(), |name| {
+            name.to_snake_case()
+        });
+        for name in names {
+            let snake_name = name.to_snake_case();
+            if crate::is_rust_keyword(&snake_name) {
+                self.push(
+                    Severity::Warning,
+                    &format!("{}.{}", location, name),
+                    format!(
+                        "{} `{}` is a Rust keyword, so it is generated as `{}`",
+                        kind,
+                        name,
+                        crate::generate_ident(&snake_name)
+                    ),
+                );
+            }
+        }
+    }
+
+    fn check_type(&mut self, generics: &[String], ty: &IdlType, location: &str) {
+        match ty {
+            IdlType::Option(inner)
+            | IdlType::Vec(inner)
+            | IdlType::Array(inner, _)
+            | IdlType::GenericLenArray(inner, _) => self.check_type(generics, inner, location),
+            // Paths refer to external types.
+            IdlType::Defined(name) | IdlType::DefinedWithTypeArgs { name, .. }
+                if !name.contains("::") && self.find_def(name).is_none() =>
+            {
+                self.push(
+                    Severity::Error,
+                    location,
+                    format!("type `{}` is not defined", name),
+                );
+            }
+            IdlType::DefinedWithTypeArgs { args, .. } => {
+                for arg in args {
+                    if let anchor_syn::idl::types::IdlDefinedTypeArg::Type(ty) = arg {
+                        self.check_type(generics, ty, location);
+                    }
+                }
+            }
+            IdlType::Generic(name) if !generics.contains(name) => self.push(
+                Severity::Error,
+                location,
+                format!("generic `{}` is not declared", name),
+            ),
+            _ => {}
+        }
+    }
+
+    fn check_fields(
+        &mut self,
+        kind: &str,
+        generics: &[String],
+        fields: &[IdlField],
+        location: &str,
+    ) {
+        self.check_field_names(
+            kind,
+            location,
+            fields.iter().map(|field| field.name.as_str()),
+        );
+        for field in fields {
+            self.check_type(generics, &field.ty, &format!("{}.{}", location, field.name));
+        }
+    }
+
+    fn check_typedef(&mut self, def: &IdlTypeDefinition, location: &str) {
+        let generics = def.generics.as_deref().unwrap_or_default();
+        match &def.ty {
+            IdlTypeDefinitionTy::Struct { fields } => {
+                self.check_fields("field", generics, fields, &format!("{}.fields", location))
+            }
+            IdlTypeDefinitionTy::Enum { variants } => {
+                let variants_location = format!("{}.variants", location);
+                self.check_names(
+                    "variant",
+                    &variants_location,
+                    variants.iter().map(|variant| variant.name.as_str()),
+                    str::to_string,
+                );
+                for variant in variants {
+                    let location = format!("{}.{}", variants_location, variant.name);
+                    match &variant.fields {
+                        Some(EnumFields::Named(fields)) => {
+                            self.check_fields("field", generics, fields, &location)
+                        }
+                        Some(EnumFields::Tuple(types)) => {
+                            for (index, ty) in types.iter().enumerate() {
+                                self.check_type(generics, ty, &format!("{}.{}", location, index));
+                            }
+                        }
+                        None => {}
+                    }
+                }
+            }
+            IdlTypeDefinitionTy::Alias { value } => self.check_type(generics, value, location),
+        }
+    }
+
+    fn check_struct_opts(&mut self, struct_opts: &BTreeMap<String, StructOpts>) {
+        for (name, opts) in struct_opts {
+            let location = format!("options.{}", name);
+            // Undefined structs are rejected when resolving the macro arguments.
+            let Some(def) = self.find_def(name) else {
+                continue;
+            };
+            let IdlTypeDefinitionTy::Struct { .. } = &def.ty else {
+                self.push(
+                    Severity::Error,
+                    &location,
+                    format!(
+                        "`{}` is listed as zero copy or packed, but is not a struct",
+                        name
+                    ),
+                );
+                continue;
+            };
+            // Fields of zero copy structs are checked by `validate_struct_opts`.
+            if !opts.zero_copy {
+                self.push(
+                    Severity::Warning,
+                    &location,
+                    format!(
+                        "`{}` is listed as packed but not as zero copy, so it is not packed",
+                        name
+                    ),
+                );
+            }
+        }
+    }
+}
+
+/// Checks an IDL and the struct options of the generator for issues which would make the
+/// generated code fail to compile or misbehave.
+pub fn lint(idl: &Idl, struct_opts: &BTreeMap<String, StructOpts>) -> Vec<LintFinding> {
+    let defs = crate::get_resolvable_typedefs(idl);
+    let mut linter = Linter {
+        defs: defs.iter().collect(),
+        findings: vec![],
+    };
+
+    // Types and accounts are both re-exported from the root of the generated crate.
+    linter.check_names(
+        "type",
+        "types",
+        idl.types
+            .iter()
+            .chain(&idl.accounts)
+            .map(|def| def.name.as_str()),
+        str::to_string,
+    );
+    for def in &idl.types {
+        linter.check_typedef(def, &format!("types.{}", def.name));
+    }
+    for def in &idl.accounts {
+        linter.check_typedef(def, &format!("accounts.{}", def.name));
+    }
+
+    let ix_names = || idl.instructions.iter().map(|ix| ix.name.as_str());
+    linter.check_names("instruction", "instructions", ix_names(), |name| {
+        name.to_snake_case()
+    });
+    linter.check_names("instruction", "instructions", ix_names(), |name| {
+        name.to_pascal_case()
+    });
+    for ix in &idl.instructions {
+        let location = format!("instructions.{}.args", ix.name);
+        linter.check_fields("argument", &[], &ix.args, &location);
+        if let Some(returns) = &ix.returns {
+            linter.check_type(&[], returns, &format!("instructions.{}.returns", ix.name));
+        }
+    }
+
+    if let Some(events) = &idl.events {
+        linter.check_names(
+            "event",
+            "events",
+            events.iter().map(|event| event.name.as_str()),
+            str::to_string,
+        );
+        for event in events {
+            let location = format!("events.{}.fields", event.name);
+            linter.check_field_names(
+                "field",
+                &location,
+                event.fields.iter().map(|f| f.name.as_str()),
+            );
+            for field in &event.fields {
+                linter.check_type(&[], &field.ty, &format!("{}.{}", location, field.name));
+            }
+        }
+    }
+
+    if let Some(errors) = &idl.errors {
+        linter.check_names(
+            "error",
+            "errors",
+            errors.iter().map(|error| error.name.as_str()),
+            str::to_string,
+        );
+    }
+
+    linter.check_names(
+        "constant",
+        "constants",
+        idl.constants.iter().map(|constant| constant.name.as_str()),
+        str::to_string,
+    );
+    for constant in &idl.constants {
+        linter.check_type(&[], &constant.ty, &format!("constants.{}", constant.name));
+    }
+
+    linter.check_struct_opts(struct_opts);
+    linter.findings
+}
+
+/// Generates compiler diagnostics reporting lint findings at `span`.
+///
+/// Errors become `compile_error!` invocations, while warnings are emitted as uses of
+/// deprecated items, as procedural macros cannot emit warnings on stable Rust.
+pub fn generate_lint_diagnostics(findings: &[LintFinding], span: Span) -> TokenStream {
+    let diagnostics = findings.iter().enumerate().map(|(index, finding)| {
+        let message = finding.to_string();
+        match finding.severity {
+            Severity::Error => syn::Error::new(span, message).to_compile_error(),
+            Severity::Warning => {
+                let name = format_ident!("anchor_gen_lint_{}", index, span = span);
+                quote_spanned! {span=>
+                    const _: () = {
+                        #[deprecated(note = #message)]
+                        #[allow(non_upper_case_globals)]
+                        const #name: () = ();
+                        #name
+                    };
+                }
+            }
+        }
+    });
+    quote! {
+        #(#diagnostics)*
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lint_json(json: &str, struct_opts: &[(&str, StructOpts)]) -> Vec<LintFinding> {
+        let (idl, _) = crate::parse_idl(json).unwrap();
+        let struct_opts = struct_opts
+            .iter()
+            .map(|(name, opts)| (name.to_string(), *opts))
+            .collect();
+        lint(&idl, &struct_opts)
+    }
+
+    fn messages(findings: &[LintFinding]) -> Vec<(Severity, &str, &str)> {
+        findings
+            .iter()
+            .map(|finding| {
+                (
+                    finding.severity,
+                    finding.location.as_str(),
+                    finding.message.as_str(),
+                )
+            })
+            .collect()
+    }
+
+    const TYPES: &str = r#"{
+        "version": "0.1.0",
+        "name": "test",
+        "instructions": [],
+        "types": [
+            {
+                "name": "Pool",
+                "type": {
+                    "kind": "struct",
+                    "fields": [
+                        { "name": "feeRate", "type": "u16" },
+                        { "name": "fee_rate", "type": "u16" },
+                        { "name": "type", "type": "u8" },
+                        { "name": "config", "type": { "defined": "Config" } }
+                    ]
+                }
+            },
+            {
+                "name": "Side",
+                "type": { "kind": "enum", "variants": [{ "name": "Bid" }, { "name": "Bid" }] }
+            },
+            {
+                "name": "Tick",
+                "type": { "kind": "struct", "fields": [{ "name": "index", "type": "i32" }] }
+            }
+        ]
+    }"#;
+
+    #[test]
+    fn lint_clean_idl() {
+        let findings = lint_json(
+            r#"{
+                "version": "0.1.0",
+                "name": "test",
+                "instructions": [
+                    {
+                        "name": "swap",
+                        "accounts": [],
+                        "args": [{ "name": "amount", "type": "u64" }]
+                    }
+                ]
+            }"#,
+            &[],
+        );
+        assert_eq!(findings, []);
+    }
+
+    #[test]
+    fn lint_typedefs() {
+        let findings = lint_json(TYPES, &[]);
+        assert_eq!(
+            messages(&findings),
+            [
+                (
+                    Severity::Error,
+                    "types.Pool.fields.fee_rate",
+                    "fields `feeRate` and `fee_rate` both generate `fee_rate`"
+                ),
+                (
+                    Severity::Warning,
+                    "types.Pool.fields.type",
+                    "field `type` is a Rust keyword, so it is generated as `r#type`"
+                ),
+                (
+                    Severity::Error,
+                    "types.Pool.fields.config",
+                    "type `Config` is not defined"
+                ),
+                (
+                    Severity::Error,
+                    "types.Side.variants.Bid",
+                    "duplicate variant `Bid`"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn lint_instructions() {
+        let findings = lint_json(
+            r#"{
+                "version": "0.1.0",
+                "name": "test",
+                "instructions": [
+                    { "name": "initPool", "accounts": [], "args": [] },
+                    { "name": "init_pool", "accounts": [], "args": [] },
+                    {
+                        "name": "move",
+                        "accounts": [],
+                        "args": [{ "name": "self", "type": "u8" }]
+                    }
+                ]
+            }"#,
+            &[],
+        );
+        let messages = messages(&findings);
+        assert_eq!(messages.len(), 3, "{:?}", messages);
+        assert_eq!(
+            messages[0],
+            (
+                Severity::Error,
+                "instructions.init_pool",
+                "instructions `initPool` and `init_pool` both generate `init_pool`"
+            )
+        );
+        assert_eq!(
+            messages[1],
+            (
+                Severity::Error,
+                "instructions.init_pool",
+                "instructions `initPool` and `init_pool` both generate `InitPool`"
+            )
+        );
+        assert_eq!(
+            messages[2],
+            (
+                Severity::Warning,
+                "instructions.move.args.self",
+                "argument `self` is a Rust keyword, so it is generated as `self_`"
+            )
+        );
+    }
+
+    #[test]
+    fn lint_struct_opts() {
+        let packed = StructOpts {
+            packed: true,
+            ..Default::default()
+        };
+        let zero_copy = StructOpts {
+            zero_copy: true,
+            ..Default::default()
+        };
+        let findings = lint_json(TYPES, &[("Side", zero_copy), ("Tick", packed)]);
+        let messages = messages(&findings);
+        assert!(messages.contains(&(
+            Severity::Error,
+            "options.Side",
+            "`Side` is listed as zero copy or packed, but is not a struct"
+        )));
+        assert!(messages.contains(&(
+            Severity::Warning,
+            "options.Tick",
+            "`Tick` is listed as packed but not as zero copy, so it is not packed"
+        )));
+    }
+}
@@ -0,0 +1,272 @@
+use anchor_syn::idl::types::{
+    IdlAccount, IdlAccountItem, IdlInstruction, IdlPda, IdlSeed, IdlType,
+};
+use heck::{ToPascalCase, ToSnakeCase};
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+
+/// An account of an instruction, flattened out of its nested accounts.
+#[derive(Clone)]
+pub struct FlatAccount<'a> {
+    /// Path of the account in the IDL, e.g. `group.account`.
+    pub path: String,
+    /// Fields to access the account in the client accounts struct of the instruction.
+    pub access: TokenStream,
+    pub account: &'a IdlAccount,
+}
+
+/// Flattens the accounts of an instruction, in the order they are passed.
+///
+/// `struct_name` is the name of the client accounts struct holding `accounts`.
+pub fn flatten_accounts<'a>(
+    struct_name: &str,
+    prefix: &str,
+    accounts: &'a [IdlAccountItem],
+) -> Vec<FlatAccount<'a>> {
+    accounts
+        .iter()
+        .flat_map(|item| match item {
+            IdlAccountItem::IdlAccount(account) => {
+                let field = crate::generate_ident(&account.name.to_snake_case());
+                vec![FlatAccount {
+                    path: format!("{}{}", prefix, account.name),
+                    access: quote! { #field },
+                    account,
+                }]
+            }
+            IdlAccountItem::IdlAccounts(inner) => {
+                let field = format_ident!("{}_{}", struct_name, inner.name.to_snake_case());
+                let sub_name = format!("{}{}", struct_name, inner.name.to_pascal_case());
+                flatten_accounts(
+                    &sub_name,
+                    &format!("{}{}.", prefix, inner.name),
+                    &inner.accounts,
+                )
+                .into_iter()
+                .map(|flat| {
+                    let access = flat.access;
+                    FlatAccount {
+                        access: quote! { #field.#access },
+                        ..flat
+                    }
+                })
+                .collect()
+            }
+        })
+        .collect()
+}
+
+/// Normalizes an account or argument path, as legacy IDLs mix naming conventions.
+pub fn normalize_path(path: &str) -> String {
+    path.split('.')
+        .map(|segment| segment.to_snake_case())
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// Name of the function deriving the address of an account.
+pub fn pda_fn_name(path: &str) -> String {
+    format!("find_{}_address", normalize_path(path).replace('.', "_"))
+}
+
+/// Returns the bytes of a constant seed.
+pub fn const_seed_bytes(value: &serde_json::Value) -> Option<Vec<u8>> {
+    match value {
+        serde_json::Value::Array(bytes) => bytes
+            .iter()
+            .map(|byte| byte.as_u64().and_then(|byte| u8::try_from(byte).ok()))
+            .collect(),
+        serde_json::Value::String(s) => Some(s.as_bytes().to_vec()),
+        _ => None,
+    }
+}
+
+/// Type of the parameter taking the value of a seed, and whether it is passed by reference.
+fn seed_param_type(ty: &IdlType) -> Option<(TokenStream, bool)> {
+    Some(match ty {
+        IdlType::PublicKey => (quote! { &Pubkey }, true),
+        IdlType::String => (quote! { &str }, true),
+        IdlType::Bytes => (quote! { &[u8] }, true),
+        IdlType::Array(inner, len) if **inner == IdlType::U8 => (quote! { &[u8; #len] }, true),
+        IdlType::Bool
+        | IdlType::U8
+        | IdlType::I8
+        | IdlType::U16
+        | IdlType::I16
+        | IdlType::U32
+        | IdlType::I32
+        | IdlType::U64
+        | IdlType::I64
+        | IdlType::U128
+        | IdlType::I128 => (crate::ty_to_rust_type(ty).parse().ok()?, false),
+        _ => return None,
+    })
+}
+
+/// Converts the parameter taking the value of a seed to the bytes of the seed.
+fn seed_param_bytes(ty: &IdlType, name: &proc_macro2::Ident) -> TokenStream {
+    match ty {
+        IdlType::PublicKey => quote! { #name.as_ref() },
+        IdlType::String => quote! { #name.as_bytes() },
+        IdlType::Bytes => quote! { #name },
+        IdlType::Array(..) => quote! { &#name[..] },
+        IdlType::Bool => quote! { &[#name as u8][..] },
+        _ => quote! { &#name.to_le_bytes()[..] },
+    }
+}
+
+/// Type of the value of a seed, if it is not constant.
+pub fn seed_type(seed: &IdlSeed) -> Option<&IdlType> {
+    match seed {
+        IdlSeed::Const(_) => None,
+        IdlSeed::Arg(seed) => Some(&seed.ty),
+        IdlSeed::Account(seed) => Some(&seed.ty),
+    }
+}
+
+/// A parameter of the function deriving the address of an account.
+pub struct PdaParam<'a> {
+    pub name: String,
+    pub seed: &'a IdlSeed,
+    /// Whether the parameter is passed by reference.
+    pub by_ref: bool,
+    ty: TokenStream,
+}
+
+/// Name of the parameter taking the value of a seed, if it is not constant.
+fn seed_param_name(seed: &IdlSeed) -> Option<String> {
+    match seed {
+        IdlSeed::Const(_) => None,
+        IdlSeed::Arg(arg) => Some(normalize_path(&arg.path).replace('.', "_")),
+        IdlSeed::Account(account) => Some(normalize_path(&account.path).replace('.', "_")),
+    }
+}
+
+/// Lists the parameters of the function deriving the address of an account, in order of
+/// first use.
+///
+/// Returns `None` if a seed cannot be converted to bytes.
+pub fn get_pda_params(pda: &IdlPda) -> Option<Vec<PdaParam<'_>>> {
+    let mut params: Vec<PdaParam> = vec![];
+    for seed in pda.seeds.iter().chain(&pda.program_id) {
+        let ty = match seed {
+            IdlSeed::Const(seed) => {
+                const_seed_bytes(&seed.value)?;
+                continue;
+            }
+            _ => seed_type(seed)?,
+        };
+        let (param_ty, by_ref) = seed_param_type(ty)?;
+        let mut name = seed_param_name(seed)?;
+        match params.iter().find(|param| param.name == name) {
+            Some(param) if param.seed == seed => continue,
+            // Args and accounts may share a name.
+            Some(_) => name = format!("arg_{}", name),
+            None => {}
+        }
+        params.push(PdaParam {
+            name,
+            seed,
+            by_ref,
+            ty: param_ty,
+        });
+    }
+    Some(params)
+}
+
+/// Generates the bytes of a seed, or `None` if it cannot be converted to bytes.
+fn generate_seed_bytes(seed: &IdlSeed, params: &[PdaParam]) -> Option<TokenStream> {
+    match seed {
+        IdlSeed::Const(seed) => {
+            let bytes = const_seed_bytes(&seed.value)?;
+            Some(quote! { &[#(#bytes),*][..] })
+        }
+        IdlSeed::Arg(_) | IdlSeed::Account(_) => {
+            let param = params.iter().find(|param| param.seed == seed)?;
+            let name = crate::generate_ident(&param.name);
+            Some(seed_param_bytes(seed_type(seed)?, &name))
+        }
+    }
+}
+
+/// Generates the function deriving the address of an account from its seeds.
+///
+/// Returns `None` if a seed cannot be converted to bytes.
+pub fn generate_pda_fn(ix_name: &str, path: &str, pda: &IdlPda) -> Option<TokenStream> {
+    let params = get_pda_params(pda)?;
+    let fn_name = format_ident!("{}", pda_fn_name(path));
+    let doc = format!(
+        " Derives the address of the `{}` account of the `{}` instruction.",
+        normalize_path(path),
+        ix_name.to_snake_case()
+    );
+    let args = params.iter().map(|param| {
+        let name = crate::generate_ident(&param.name);
+        let ty = &param.ty;
+        quote! { #name: #ty }
+    });
+    let seeds = pda
+        .seeds
+        .iter()
+        .map(|seed| generate_seed_bytes(seed, &params))
+        .collect::<Option<Vec<_>>>()?;
+    let program_id = match &pda.program_id {
+        None => quote! { &crate::ID },
+        Some(IdlSeed::Const(seed)) => {
+            let bytes = const_seed_bytes(&seed.value)?;
+            if bytes.len() != 32 {
+                return None;
+            }
+            quote! { &Pubkey::new_from_array([#(#bytes),*]) }
+        }
+        Some(seed) => {
+            if seed_type(seed)? != &IdlType::PublicKey {
+                return None;
+            }
+            let param = params.iter().find(|param| param.seed == seed)?;
+            let name = crate::generate_ident(&param.name);
+            quote! { #name }
+        }
+    };
+    Some(quote! {
+        #[doc = #doc]
+        pub fn #fn_name(#(#args),*) -> (Pubkey, u8) {
+            Pubkey::find_program_address(&[#(#seeds),*], #program_id)
+        }
+    })
+}
+
+/// Generates the functions deriving the addresses of the accounts of an instruction.
+pub fn generate_ix_pdas(ix: &IdlInstruction) -> TokenStream {
+    let fns = flatten_accounts("", "", &ix.accounts)
+        .into_iter()
+        .filter_map(|flat| generate_pda_fn(&ix.name, &flat.path, flat.account.pda.as_ref()?));
+    quote! {
+        #(#fns)*
+    }
+}
+
+/// Generates the `pda` module contents: one module per instruction with derivable accounts.
+pub fn generate_pdas(ixs: &[IdlInstruction]) -> TokenStream {
+    let modules = ixs.iter().filter_map(|ix| {
+        let fns = generate_ix_pdas(ix);
+        if fns.is_empty() {
+            return None;
+        }
+        let mod_name = crate::generate_ident(&ix.name.to_snake_case());
+        let doc = format!(
+            " Addresses of the accounts of the `{}` instruction.",
+            ix.name.to_snake_case()
+        );
+        Some(quote! {
+            #[doc = #doc]
+            pub mod #mod_name {
+                use super::*;
+                #fns
+            }
+        })
+    });
+    quote! {
+        #(#modules)*
+    }
+}
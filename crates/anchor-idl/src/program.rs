@@ -1,21 +1,34 @@
 use heck::{ToPascalCase, ToSnakeCase};
 use std::{
-    collections::{BTreeMap, HashMap, HashSet},
-    fs,
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    env, fs,
+    path::PathBuf,
 };
 
-#[cfg(feature = "glam")]
-use std::{env, path::PathBuf};
-
+use anchor_syn::idl::types::IdlTypeDefinitionTy;
 use darling::{util::PathList, FromMeta};
-use proc_macro2::{Ident, TokenStream};
+use proc_macro2::{Ident, Span, TokenStream};
 use quote::{format_ident, quote};
 use serde::{Deserialize, Serialize};
-use serde_yaml;
+use syn::{
+    bracketed,
+    ext::IdentExt,
+    parenthesized,
+    parse::{Parse, ParseStream, Parser},
+    punctuated::Punctuated,
+    spanned::Spanned,
+    token::Paren,
+    Attribute, LitStr, Meta, NestedMeta, Path, Token,
+};
 
 use crate::{
-    generate_accounts, generate_glam_ix_handlers, generate_glam_ix_structs, generate_ix_handlers,
-    generate_ix_structs, generate_typedefs, GlamIxRemapping, GEN_VERSION,
+    apply_type_overrides, generate_account_decoder, generate_accounts, generate_client,
+    generate_constants, generate_errors, generate_events, generate_glam_ix_handlers,
+    generate_glam_ix_structs, generate_int256_types, generate_ix_decoder, generate_ix_handlers,
+    generate_ix_structs, generate_lint_diagnostics, generate_pdas, generate_pod_int128_types,
+    generate_typedefs, get_generated_traits, get_referenced_events, lint, parse_idl,
+    prune_typedefs, validate_idl, validate_instruction_discriminators, validate_struct_opts,
+    GeneratorError, GlamIxRemapping, IdlExtensions, LintFinding, Severity, GEN_VERSION,
 };
 
 #[derive(Debug, Default, Serialize, Deserialize, Clone)]
@@ -47,76 +60,485 @@ pub struct GeneratorOptions {
     pub idl_name_alias: Option<String>,
     /// GLAM autogen config yaml.
     pub glam_codegen_config: Option<String>,
-    /// List of zero copy structs.
+    /// List of zero copy structs, replacing those inferred from the IDL.
     pub zero_copy: Option<PathList>,
-    /// List of `repr(packed)` structs.
+    /// List of `repr(C, packed)` structs, replacing those inferred from the IDL.
     pub packed: Option<PathList>,
+    /// External types replacing typedefs, e.g. `type_overrides(Mint = "spl_token::state::Mint")`.
+    pub type_overrides: Option<HashMap<String, String>>,
+    /// Generate `u128` and `i128` fields of zero copy structs as `PodU128` and `PodI128`.
+    #[darling(default)]
+    pub aligned_int128: bool,
+    /// Typedefs to generate even if the selected instructions do not use them.
+    ///
+    /// Requires the `instructions` option, without which every typedef is generated.
+    pub keep_types: Option<PathList>,
+    /// Extra attributes of typedefs, from the `derive` and `attr` options.
+    #[darling(skip)]
+    pub type_attrs: Vec<TypeAttrs>,
+    /// Instructions to generate, from the `instructions` option.
+    #[darling(skip)]
+    pub instructions: Option<InstructionFilter>,
+}
+
+/// Subset of the instructions of the IDL to generate, e.g. from
+/// `instructions(include = [deposit, withdraw])` or `instructions(exclude = [migrate])`.
+///
+/// Names are matched in snake case, so `initializeUser` and `initialize_user` are the same
+/// instruction. Only the typedefs used by the selected instructions, the accounts, events and
+/// constants, or listed by the `keep_types` option are generated.
+#[derive(Clone, Debug, Default)]
+pub struct InstructionFilter {
+    /// Instructions to keep, or all of them if `None`.
+    pub include: Option<Vec<Ident>>,
+    /// Instructions to drop, or none of them if `None`.
+    pub exclude: Option<Vec<Ident>>,
+}
+
+impl InstructionFilter {
+    /// Parses the contents of an `instructions(include = [...], exclude = [...])` option.
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut filter = InstructionFilter::default();
+        while !input.is_empty() {
+            let key: Ident = input.parse()?;
+            input.parse::<Token![=]>()?;
+            let content;
+            bracketed!(content in input);
+            // Keywords are accepted, so that instructions named after them can be excluded.
+            let names: Vec<Ident> =
+                Punctuated::<Ident, Token![,]>::parse_terminated_with(&content, Ident::parse_any)?
+                    .into_iter()
+                    .collect();
+            let slot = if key == "include" {
+                &mut filter.include
+            } else if key == "exclude" {
+                &mut filter.exclude
+            } else {
+                return Err(syn::Error::new(
+                    key.span(),
+                    "expected `include = [...]` or `exclude = [...]`",
+                ));
+            };
+            if slot.is_some() {
+                return Err(syn::Error::new(
+                    key.span(),
+                    format!("`{}` is given more than once", key),
+                ));
+            }
+            if key == "include" && names.is_empty() {
+                return Err(syn::Error::new(
+                    key.span(),
+                    "`include = []` would generate no instructions",
+                ));
+            }
+            *slot = Some(names);
+            if !input.is_empty() {
+                input.parse::<Token![,]>()?;
+            }
+        }
+        Ok(filter)
+    }
+
+    /// Checks that the listed instructions are defined by the IDL, and removes the others
+    /// from it.
+    fn apply(&self, idl: &mut anchor_syn::idl::types::Idl) -> Result<(), GeneratorError> {
+        for name in self.include.iter().chain(&self.exclude).flatten() {
+            let snake_name = name.to_string().to_snake_case();
+            if !idl
+                .instructions
+                .iter()
+                .any(|ix| ix.name.to_snake_case() == snake_name)
+            {
+                return Err(GeneratorError::UnknownInstruction {
+                    name: name.to_string(),
+                    span: name.span(),
+                });
+            }
+        }
+        let listed = |names: &[Ident], ix_name: &str| {
+            names
+                .iter()
+                .any(|name| name.to_string().to_snake_case() == ix_name.to_snake_case())
+        };
+        idl.instructions.retain(|ix| {
+            self.include
+                .as_ref()
+                .map_or(true, |include| listed(include, &ix.name))
+                && !self
+                    .exclude
+                    .as_ref()
+                    .is_some_and(|exclude| listed(exclude, &ix.name))
+        });
+        Ok(())
+    }
+}
+
+/// Extra attributes of typedefs, e.g. from `derive(PartialEq, Eq => [Side, MarketType])`.
+#[derive(Clone, Debug)]
+pub struct TypeAttrs {
+    /// Name of the macro option, i.e. `derive` or `attr`.
+    pub option: &'static str,
+    pub attrs: Vec<Attribute>,
+    /// Names of the typedefs getting the attributes.
+    pub types: Vec<Ident>,
+}
+
+impl TypeAttrs {
+    /// Parses the contents of a `derive(Trait, ... => [Type, ...])` or
+    /// `attr("#[...]" => [Type, ...])` option.
+    fn parse(option: &Ident, input: ParseStream) -> syn::Result<Self> {
+        let (option, attrs) = if option == "derive" {
+            let mut traits: Punctuated<Path, Token![,]> = Punctuated::new();
+            while !input.peek(Token![=>]) {
+                traits.push_value(input.parse()?);
+                if input.peek(Token![=>]) {
+                    break;
+                }
+                traits.push_punct(input.parse()?);
+            }
+            let attrs = Attribute::parse_outer.parse2(quote! { #[derive(#traits)] })?;
+            ("derive", attrs)
+        } else {
+            let lit: LitStr = input.parse()?;
+            let attrs = Attribute::parse_outer
+                .parse_str(&lit.value())
+                .map_err(|err| syn::Error::new(lit.span(), err))?;
+            ("attr", attrs)
+        };
+        input.parse::<Token![=>]>()?;
+        let content;
+        bracketed!(content in input);
+        let types = Punctuated::<Ident, Token![,]>::parse_terminated(&content)?;
+        Ok(TypeAttrs {
+            option,
+            attrs,
+            types: types.into_iter().collect(),
+        })
+    }
+}
+
+/// Arguments of the generator macros.
+///
+/// The `derive`, `attr` and `instructions` options do not follow the attribute syntax parsed
+/// by darling, so they are parsed apart from the other arguments.
+pub struct MacroArgs {
+    pub args: Vec<NestedMeta>,
+    pub type_attrs: Vec<TypeAttrs>,
+    pub instructions: Option<InstructionFilter>,
+}
+
+impl Parse for MacroArgs {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut args = vec![];
+        let mut type_attrs = vec![];
+        let mut instructions = None;
+        while !input.is_empty() {
+            let fork = input.fork();
+            match fork.parse::<Ident>() {
+                Ok(option) if (option == "derive" || option == "attr") && fork.peek(Paren) => {
+                    input.parse::<Ident>()?;
+                    let content;
+                    parenthesized!(content in input);
+                    type_attrs.push(TypeAttrs::parse(&option, &content)?);
+                }
+                Ok(option) if option == "instructions" && fork.peek(Paren) => {
+                    if instructions.is_some() {
+                        return Err(syn::Error::new(
+                            option.span(),
+                            "duplicate `instructions` option",
+                        ));
+                    }
+                    input.parse::<Ident>()?;
+                    let content;
+                    parenthesized!(content in input);
+                    instructions = Some(InstructionFilter::parse(&content)?);
+                }
+                _ => args.push(input.parse()?),
+            }
+            if !input.is_empty() {
+                input.parse::<Token![,]>()?;
+            }
+        }
+        Ok(MacroArgs {
+            args,
+            type_attrs,
+            instructions,
+        })
+    }
 }
 
-fn path_list_to_string(list: Option<&PathList>) -> HashSet<String> {
+impl MacroArgs {
+    /// Converts the arguments to [GeneratorOptions].
+    pub fn to_options(&self) -> darling::Result<GeneratorOptions> {
+        Ok(GeneratorOptions {
+            type_attrs: self.type_attrs.clone(),
+            instructions: self.instructions.clone(),
+            ..GeneratorOptions::from_list(&self.args)?
+        })
+    }
+}
+
+/// Lists the names of a struct list argument, checking that each is a struct of the IDL.
+fn path_list_to_string(
+    argument: &'static str,
+    list: Option<&PathList>,
+    idl: &anchor_syn::idl::types::Idl,
+) -> Result<HashSet<String>, GeneratorError> {
     list.map(|el| {
         el.iter()
-            .map(|el| el.get_ident().unwrap().to_string())
+            .map(|el| {
+                let name = el
+                    .get_ident()
+                    .ok_or_else(|| GeneratorError::InvalidArgument {
+                        argument,
+                        message: "expected a list of struct names".to_string(),
+                    })?
+                    .to_string();
+                if !idl
+                    .types
+                    .iter()
+                    .chain(&idl.accounts)
+                    .any(|def| def.name == name)
+                {
+                    return Err(GeneratorError::UnknownStruct { argument, name });
+                }
+                Ok(name)
+            })
             .collect()
     })
-    .unwrap_or_default()
+    .unwrap_or_else(|| Ok(HashSet::new()))
+}
+
+/// Resolves an IDL path relative to the manifest of the crate being compiled, falling back
+/// to the path as given (e.g. when running outside of Cargo).
+///
+/// Without the `glam` feature, earlier versions resolved relative paths against the working
+/// directory of the compiler, i.e. the workspace root. A path relative to the workspace root
+/// which does not also exist relative to the manifest still resolves as before, but the
+/// manifest directory now takes precedence when both exist.
+fn resolve_idl_path(idl_path: &str) -> PathBuf {
+    env::var("CARGO_MANIFEST_DIR")
+        .map(|dir| PathBuf::from(dir).join(idl_path))
+        .ok()
+        .filter(|path| path.exists())
+        .unwrap_or_else(|| PathBuf::from(idl_path))
 }
 
 impl GeneratorOptions {
+    /// Builds the [Generator].
+    ///
+    /// # Panics
+    ///
+    /// Panics on invalid options or IDLs; see [GeneratorOptions::try_to_generator].
     pub fn to_generator(&self) -> Generator {
-        #[cfg(feature = "glam")]
-        let cargo_manifest_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
-        #[cfg(feature = "glam")]
-        let path = PathBuf::from(cargo_manifest_dir).join(&self.idl_path);
-        #[cfg(feature = "glam")]
-        let idl_contents = fs::read_to_string(&path).unwrap();
-
-        #[cfg(not(feature = "glam"))]
-        let idl_contents = fs::read_to_string(&self.idl_path).unwrap();
-
-        let idl: anchor_syn::idl::types::Idl = serde_json::from_str(&idl_contents).unwrap();
-
-        let zero_copy = path_list_to_string(self.zero_copy.as_ref());
-        let packed = path_list_to_string(self.packed.as_ref());
-
-        let mut struct_opts: BTreeMap<String, StructOpts> = BTreeMap::new();
-        let all_structs: HashSet<&String> = zero_copy.union(&packed).collect::<HashSet<_>>();
-        all_structs.into_iter().for_each(|name| {
-            struct_opts.insert(
-                name.to_string(),
-                StructOpts {
-                    zero_copy: zero_copy.contains(name),
-                    packed: packed.contains(name),
-                },
-            );
-        });
+        self.try_to_generator()
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Builds the [Generator], validating the options and the IDL.
+    pub fn try_to_generator(&self) -> Result<Generator, GeneratorError> {
+        let path = resolve_idl_path(&self.idl_path);
+        let idl_contents = fs::read_to_string(&path).map_err(|source| GeneratorError::ReadIdl {
+            path: path.clone(),
+            source,
+        })?;
+
+        let (mut idl, extensions) =
+            parse_idl(&idl_contents).map_err(|source| GeneratorError::ParseIdl { path, source })?;
+        validate_idl(&idl, &extensions)?;
+        if let Some(instructions) = &self.instructions {
+            instructions.apply(&mut idl)?;
+        }
+        validate_instruction_discriminators(&idl, &extensions)?;
+
+        let mut type_overrides = BTreeMap::new();
+        for (name, path) in self.type_overrides.iter().flatten() {
+            if !idl.types.iter().any(|def| def.name == *name) {
+                return Err(GeneratorError::UnknownOverride { name: name.clone() });
+            }
+            match syn::parse_str::<Path>(path) {
+                Ok(parsed) if parsed.leading_colon.is_some() || parsed.segments.len() > 1 => {}
+                _ => {
+                    return Err(GeneratorError::InvalidOverride {
+                        name: name.clone(),
+                        path: path.clone(),
+                    })
+                }
+            }
+            type_overrides.insert(name.clone(), path.clone());
+        }
+        apply_type_overrides(&mut idl, &type_overrides);
+
+        let zero_copy = path_list_to_string("zero_copy", self.zero_copy.as_ref(), &idl)?;
+        let packed = path_list_to_string("packed", self.packed.as_ref(), &idl)?;
+
+        // The layouts of 0.30 IDLs tell which structs are zero copy and packed, unless the
+        // macro lists them.
+        let mut struct_opts: BTreeMap<String, StructOpts> = idl
+            .types
+            .iter()
+            .chain(&idl.accounts)
+            .filter(|def| matches!(def.ty, IdlTypeDefinitionTy::Struct { .. }))
+            .filter_map(|def| {
+                let opts = extensions.type_layouts.get(&def.name)?.struct_opts();
+                opts.zero_copy.then(|| (def.name.clone(), opts))
+            })
+            .collect();
+        if self.zero_copy.is_some() {
+            struct_opts.retain(|name, _| zero_copy.contains(name));
+        }
+        if self.packed.is_some() {
+            for (name, opts) in struct_opts.iter_mut() {
+                opts.packed = packed.contains(name);
+            }
+        }
+        for name in &zero_copy {
+            struct_opts.entry(name.clone()).or_default().zero_copy = true;
+        }
+        for name in &packed {
+            struct_opts.entry(name.clone()).or_default().packed = true;
+        }
+        for opts in struct_opts.values_mut() {
+            opts.aligned_int128 = self.aligned_int128;
+        }
+
+        let mut type_attrs: BTreeMap<String, TokenStream> = BTreeMap::new();
+        for extra in &self.type_attrs {
+            for ty in &extra.types {
+                let name = ty.to_string();
+                let def = idl
+                    .types
+                    .iter()
+                    .chain(&idl.accounts)
+                    .find(|def| def.name == name)
+                    .ok_or_else(|| GeneratorError::UnknownTypedef {
+                        argument: extra.option,
+                        name: name.clone(),
+                        span: ty.span(),
+                    })?;
+                let derives = extra
+                    .attrs
+                    .iter()
+                    .filter(|attr| attr.path.is_ident("derive"))
+                    .map(|attr| attr.parse_meta())
+                    .collect::<syn::Result<Vec<_>>>()
+                    .map_err(|err| GeneratorError::InvalidArgument {
+                        argument: extra.option,
+                        message: err.to_string(),
+                    })?;
+                if !derives.is_empty() && matches!(def.ty, IdlTypeDefinitionTy::Alias { .. }) {
+                    return Err(GeneratorError::AliasDerive {
+                        argument: extra.option,
+                        name,
+                        span: ty.span(),
+                    });
+                }
+                let opts = struct_opts.get(&name).copied().unwrap_or_default();
+                let generated = get_generated_traits(&idl.types, def, opts);
+                let derived = derives.iter().flat_map(|meta| match meta {
+                    Meta::List(list) => list.nested.iter().collect(),
+                    _ => vec![],
+                });
+                for derive in derived {
+                    let NestedMeta::Meta(derive) = derive else {
+                        continue;
+                    };
+                    let Some(trait_name) = derive.path().segments.last() else {
+                        continue;
+                    };
+                    if generated.contains(&trait_name.ident.to_string().as_str()) {
+                        return Err(GeneratorError::DuplicateDerive {
+                            argument: extra.option,
+                            name,
+                            derive: trait_name.ident.to_string(),
+                            span: ty.span(),
+                        });
+                    }
+                }
+                let attrs = &extra.attrs;
+                type_attrs
+                    .entry(name)
+                    .or_default()
+                    .extend(quote! { #(#attrs)* });
+            }
+        }
+
+        if self.keep_types.is_some() && self.instructions.is_none() {
+            return Err(GeneratorError::InvalidArgument {
+                argument: "keep_types",
+                message: "it requires `instructions(...)`, without which every typedef is \
+                          generated"
+                    .to_string(),
+            });
+        }
+        let mut keep_types = BTreeSet::new();
+        for path in self.keep_types.iter().flat_map(|list| list.iter()) {
+            let name = path
+                .get_ident()
+                .ok_or_else(|| GeneratorError::InvalidArgument {
+                    argument: "keep_types",
+                    message: "expected a list of type names".to_string(),
+                })?
+                .to_string();
+            if !idl.types.iter().any(|def| def.name == name) {
+                return Err(GeneratorError::UnknownTypedef {
+                    argument: "keep_types",
+                    name,
+                    span: path.span(),
+                });
+            }
+            keep_types.insert(name);
+        }
+        if self.instructions.is_some() {
+            prune_typedefs(&mut idl, &keep_types);
+            let is_defined = |name: &String| {
+                idl.types
+                    .iter()
+                    .chain(&idl.accounts)
+                    .any(|def| def.name == *name)
+            };
+            struct_opts.retain(|name, _| is_defined(name));
+            type_attrs.retain(|name, _| is_defined(name));
+        }
+        validate_struct_opts(&idl, &struct_opts)?;
 
         let mut ix_code_gen_configs = HashMap::new();
 
         if let Some(glam_codegen_config) = &self.glam_codegen_config {
-            let glam_autogen_config_contents = fs::read_to_string(glam_codegen_config).unwrap();
-            let config: serde_yaml::Value =
-                serde_yaml::from_str(&glam_autogen_config_contents).unwrap();
+            let path = PathBuf::from(glam_codegen_config);
+            let glam_autogen_config_contents =
+                fs::read_to_string(&path).map_err(|source| GeneratorError::ReadConfig {
+                    path: path.clone(),
+                    source,
+                })?;
+            let config: serde_yaml::Value = serde_yaml::from_str(&glam_autogen_config_contents)
+                .map_err(|source| GeneratorError::ParseConfig {
+                    path: path.clone(),
+                    source,
+                })?;
 
             let idl_name = self.idl_name_alias.clone().unwrap_or(idl.name.clone());
-            ix_code_gen_configs = config
-                .get(idl_name.as_str())
-                .unwrap()
-                .as_sequence()
-                .unwrap()
-                .iter()
-                .map(|el| serde_yaml::from_value(el.clone()).unwrap())
-                .collect::<Vec<GlamIxCodeGenConfig>>()
-                .into_iter()
-                .map(|c| (c.ix_name.clone(), c))
-                .collect();
+            let program_config = config.get(idl_name.as_str()).ok_or_else(|| {
+                GeneratorError::MissingProgramConfig {
+                    path: path.clone(),
+                    program: idl_name.clone(),
+                }
+            })?;
+            ix_code_gen_configs =
+                serde_yaml::from_value::<Vec<GlamIxCodeGenConfig>>(program_config.clone())
+                    .map_err(|source| GeneratorError::ParseConfig { path, source })?
+                    .into_iter()
+                    .map(|c| (c.ix_name.clone(), c))
+                    .collect();
         }
 
-        Generator {
+        Ok(Generator {
             idl,
+            extensions,
             struct_opts,
+            type_attrs,
             ix_code_gen_configs,
-        }
+        })
     }
 }
 
@@ -124,11 +546,24 @@ impl GeneratorOptions {
 pub struct StructOpts {
     pub packed: bool,
     pub zero_copy: bool,
+    /// Alignment of zero copy structs, e.g. from `repr(C, align(8))`.
+    pub align: Option<usize>,
+    /// Whether `u128` and `i128` fields of zero copy structs are generated as wrappers with
+    /// the layout they have on Solana.
+    pub aligned_int128: bool,
 }
 
+/// Generates code from a validated IDL.
+///
+/// Build it with [GeneratorOptions::try_to_generator]: the generation functions assume the
+/// IDL passed [validate_idl], e.g. that its names are valid identifiers.
 pub struct Generator {
     pub idl: anchor_syn::idl::types::Idl,
+    /// Information only present in Anchor 0.30+ IDLs.
+    pub extensions: IdlExtensions,
     pub struct_opts: BTreeMap<String, StructOpts>,
+    /// Extra attributes of typedefs, keyed by name.
+    pub type_attrs: BTreeMap<String, TokenStream>,
     pub ix_code_gen_configs: HashMap<String, GlamIxCodeGenConfig>,
 }
 
@@ -194,23 +629,105 @@ impl Generator {
         (quote! { #imports #ix_structs #ix_handlers }, remapping)
     }
 
-    pub fn generate_cpi_interface(&self) -> TokenStream {
+    /// Lints the IDL and the struct options.
+    pub fn lint(&self) -> Vec<LintFinding> {
+        lint(&self.idl, &self.struct_opts)
+    }
+
+    /// Generates the CPI interface after linting the IDL, reporting findings at `span`.
+    ///
+    /// Nothing is generated if an error is found, to avoid burying it under the errors of
+    /// the generated code.
+    pub fn generate_linted_cpi_interface(&self, span: Span) -> TokenStream {
+        let findings = self.lint();
+        let diagnostics = generate_lint_diagnostics(&findings, span);
+        if findings
+            .iter()
+            .any(|finding| finding.severity == Severity::Error)
+        {
+            return diagnostics;
+        }
+        let interface = self
+            .generate_cpi_interface()
+            .unwrap_or_else(|err| err.to_compile_error(span));
+        quote! {
+            #interface
+            #diagnostics
+        }
+    }
+
+    /// Generates the CPI interface of the program.
+    pub fn generate_cpi_interface(&self) -> Result<TokenStream, GeneratorError> {
         let idl = &self.idl;
+        validate_struct_opts(idl, &self.struct_opts)?;
         let program_name: Ident = format_ident!("{}", idl.name);
 
-        let accounts = generate_accounts(&idl.types, &idl.accounts, &self.struct_opts);
-        let typedefs = generate_typedefs(&idl.types, &self.struct_opts);
+        let accounts = generate_accounts(
+            &idl.types,
+            &idl.accounts,
+            &self.struct_opts,
+            &self.type_attrs,
+            &self.extensions,
+        )?;
+        let typedefs = generate_typedefs(&idl.types, &self.struct_opts, &self.type_attrs);
+        let int256_types = generate_int256_types(idl);
+        let pod_int128_types = generate_pod_int128_types(idl, &self.struct_opts);
+        let constants = generate_constants(&idl.constants)?;
+        let pdas = generate_pdas(&idl.instructions);
         let ix_handlers = generate_ix_handlers(&idl.instructions);
         let ix_structs = generate_ix_structs(&idl.instructions);
+        let client = generate_client(&idl.instructions, &self.extensions)?;
+        let ix_decoder = generate_ix_decoder(&idl.instructions, &self.extensions);
+        let account_decoder =
+            generate_account_decoder(&idl.accounts, &self.struct_opts, &self.extensions);
+        let errors = match &idl.errors {
+            Some(errors) if !errors.is_empty() => {
+                let errors = generate_errors(errors);
+                quote! {
+                    pub mod errors {
+                        //! Errors returned by the program.
+                        use super::*;
+                        #errors
+                    }
+                }
+            }
+            _ => quote! {},
+        };
+        let events = match &idl.events {
+            Some(events) if !events.is_empty() => {
+                let events = generate_events(events, &self.extensions);
+                quote! {
+                    pub mod events {
+                        //! Events emitted by the program.
+                        use super::*;
+                        #events
+                    }
+                }
+            }
+            _ => quote! {},
+        };
+        let referenced_events = get_referenced_events(idl)
+            .into_iter()
+            .map(|event| format_ident!("{}", event.name));
 
-        let docs = format!(
-            " Anchor CPI crate generated from {} v{} using [anchor-gen](https://crates.io/crates/anchor-gen) v{}.",
+        let mut docs = idl.docs.clone().unwrap_or_default();
+        if !docs.is_empty() {
+            docs.push(String::new());
+        }
+        docs.push(format!(
+            "Anchor CPI crate generated from {} v{} using [anchor-gen](https://crates.io/crates/anchor-gen) v{}.",
             &idl.name,
             &idl.version,
             &GEN_VERSION.unwrap_or("unknown")
-        );
+        ));
+        let docs = docs.iter().map(|line| {
+            let line = format!(" {}", line);
+            quote! {
+                #![doc = #line]
+            }
+        });
 
-        quote! {
+        Ok(quote! {
 
             use anchor_lang::prelude::*;
 
@@ -218,6 +735,8 @@ impl Generator {
                 //! User-defined types.
                 use super::*;
                 #typedefs
+                #int256_types
+                #pod_int128_types
             }
 
             pub mod state {
@@ -226,6 +745,18 @@ impl Generator {
                 #accounts
             }
 
+            pub mod constants {
+                //! Constants defined by the program.
+                use super::*;
+                #constants
+            }
+
+            pub mod pda {
+                //! Derivation of the program derived addresses of instruction accounts.
+                use super::*;
+                #pdas
+            }
+
             #[allow(non_snake_case)]
             pub mod ix_accounts {
                 //! Accounts used in instructions.
@@ -233,17 +764,190 @@ impl Generator {
                 #ix_structs
             }
 
+            #errors
+
+            #events
+
+            pub mod client {
+                //! Off-chain instruction builders.
+                use super::*;
+                #client
+            }
+
+            pub mod decoder {
+                //! Decoders of raw program data.
+                use super::*;
+                #ix_decoder
+                #account_decoder
+            }
+
             use ix_accounts::*;
             pub use state::*;
             pub use typedefs::*;
+            #(pub use events::#referenced_events;)*
 
             #[program]
             pub mod #program_name {
-                #![doc = #docs]
+                #(#docs)*
 
                 use super::*;
                 #ix_handlers
             }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_filter(input: TokenStream) -> InstructionFilter {
+        InstructionFilter::parse.parse2(input).unwrap()
+    }
+
+    fn ix_names(filter: &InstructionFilter) -> Result<Vec<String>, GeneratorError> {
+        let (mut idl, _) = parse_idl(
+            r#"{
+                "version": "0.1.0",
+                "name": "test",
+                "instructions": [
+                    { "name": "initPool", "accounts": [], "args": [] },
+                    { "name": "swap", "accounts": [], "args": [] },
+                    { "name": "closePool", "accounts": [], "args": [] }
+                ]
+            }"#,
+        )
+        .unwrap();
+        filter.apply(&mut idl)?;
+        Ok(idl.instructions.into_iter().map(|ix| ix.name).collect())
+    }
+
+    #[test]
+    fn instruction_filter_include() {
+        let filter = parse_filter(quote! { include = [swap, init_pool] });
+        assert_eq!(ix_names(&filter).unwrap(), ["initPool", "swap"]);
+    }
+
+    #[test]
+    fn instruction_filter_exclude() {
+        let filter = parse_filter(quote! { exclude = [close_pool] });
+        assert_eq!(ix_names(&filter).unwrap(), ["initPool", "swap"]);
+    }
+
+    #[test]
+    fn instruction_filter_include_and_exclude() {
+        let filter = parse_filter(quote! { include = [initPool, swap], exclude = [swap] });
+        assert_eq!(ix_names(&filter).unwrap(), ["initPool"]);
+    }
+
+    #[test]
+    fn instruction_filter_unknown_names() {
+        for input in [
+            quote! { include = [swap, deposit] },
+            quote! { exclude = [deposit] },
+        ] {
+            match ix_names(&parse_filter(input)) {
+                Err(GeneratorError::UnknownInstruction { name, .. }) => assert_eq!(name, "deposit"),
+                other => panic!("expected an unknown instruction, got {:?}", other),
+            }
         }
     }
+
+    #[test]
+    fn keyword_instructions_are_rejected() {
+        let (mut idl, extensions) = parse_idl(
+            r#"{
+                "version": "0.1.0",
+                "name": "test",
+                "instructions": [
+                    { "name": "move", "accounts": [], "args": [] },
+                    { "name": "swap", "accounts": [], "args": [] }
+                ]
+            }"#,
+        )
+        .unwrap();
+        // The `client` module builds the instruction data from the IDL discriminator.
+        assert_eq!(
+            extensions.instruction_discriminator("move")[..8],
+            crate::sighash("global", "move")
+        );
+        // `#[program]` would derive it from the `move_` handler instead.
+        match validate_instruction_discriminators(&idl, &extensions) {
+            Err(GeneratorError::KeywordInstruction {
+                name,
+                handler,
+                discriminator,
+                derived,
+            }) => {
+                assert_eq!((name.as_str(), handler.as_str()), ("move", "move_"));
+                assert_eq!(discriminator[..8], crate::sighash("global", "move"));
+                assert_eq!(derived[..8], crate::sighash("global", "move_"));
+            }
+            other => panic!("expected a keyword instruction, got {:?}", other),
+        }
+
+        parse_filter(quote! { exclude = [move] })
+            .apply(&mut idl)
+            .unwrap();
+        validate_instruction_discriminators(&idl, &extensions).unwrap();
+    }
+
+    fn build_generator(input: TokenStream) -> Generator {
+        let args: MacroArgs = syn::parse2(quote! {
+            idl_path = "../../examples/anchor-030-cpi/idl.json",
+            #input
+        })
+        .unwrap();
+        args.to_options().unwrap().try_to_generator().unwrap()
+    }
+
+    #[test]
+    fn inferred_struct_opts() {
+        let generator = build_generator(quote! {});
+        let packed_tick = generator.struct_opts["PackedTick"];
+        assert!(packed_tick.zero_copy && packed_tick.packed);
+        assert_eq!(generator.struct_opts["AlignedSlot"].align, Some(16));
+        assert!(generator.struct_opts["WideAccount"].zero_copy);
+    }
+
+    #[test]
+    fn listed_struct_opts_override_inferred_ones() {
+        let generator = build_generator(quote! { zero_copy(PackedTick, Market) });
+        let names: Vec<&str> = generator.struct_opts.keys().map(String::as_str).collect();
+        assert_eq!(names, ["Market", "PackedTick"]);
+        // The inferred packing is kept, as `packed` is not listed.
+        assert!(generator.struct_opts["PackedTick"].packed);
+
+        let generator = build_generator(quote! { packed(WideAccount) });
+        assert!(!generator.struct_opts["PackedTick"].packed);
+        assert!(generator.struct_opts["PackedTick"].zero_copy);
+        assert!(generator.struct_opts["WideAccount"].packed);
+    }
+
+    #[test]
+    fn instruction_filter_rejects_repeated_keys() {
+        for input in [
+            quote! { include = [swap], include = [init_pool] },
+            quote! { exclude = [swap], exclude = [init_pool] },
+            quote! { exclude = [], exclude = [swap] },
+            quote! { exclude = [swap], exclude = [] },
+        ] {
+            let err = InstructionFilter::parse.parse2(input).unwrap_err();
+            assert!(err.to_string().contains("more than once"), "{}", err);
+        }
+    }
+
+    #[test]
+    fn instruction_filter_rejects_empty_include() {
+        let err = InstructionFilter::parse
+            .parse2(quote! { include = [] })
+            .unwrap_err();
+        assert!(err.to_string().contains("no instructions"), "{}", err);
+        // Excluding nothing is allowed.
+        let filter = parse_filter(quote! { exclude = [] });
+        assert_eq!(
+            ix_names(&filter).unwrap(),
+            ["initPool", "swap", "closePool"]
+        );
+    }
 }
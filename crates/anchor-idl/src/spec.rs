@@ -0,0 +1,876 @@
+//! Loading of IDLs in both the legacy and the Anchor 0.30+ specification.
+//!
+//! IDLs emitted by Anchor 0.30 and later are converted into the legacy
+//! [anchor_syn::idl::types::Idl] model consumed by the code generators. Information
+//! which has no place in the legacy model is kept in [IdlExtensions].
+
+use std::collections::BTreeMap;
+
+use anchor_lang_idl_spec as spec;
+use anchor_syn::idl::types::{
+    EnumFields, Idl, IdlAccount, IdlAccountItem, IdlAccounts, IdlConst, IdlDefinedTypeArg,
+    IdlErrorCode, IdlEvent, IdlEventField, IdlField, IdlInstruction, IdlPda, IdlSeed,
+    IdlSeedAccount, IdlSeedArg, IdlSeedConst, IdlType, IdlTypeDefinition, IdlTypeDefinitionTy,
+};
+use heck::ToSnakeCase;
+use serde::de::Error as _;
+use sha2::{Digest, Sha256};
+
+use crate::StructOpts;
+
+/// How a type is serialized, as declared by Anchor 0.30+ IDLs.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum IdlSerialization {
+    #[default]
+    Borsh,
+    Bytemuck,
+}
+
+/// Memory representation of a type, as declared by Anchor 0.30+ IDLs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IdlRepr {
+    Rust { packed: bool, align: Option<usize> },
+    C { packed: bool, align: Option<usize> },
+    Transparent,
+}
+
+/// Layout metadata of a type definition.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct IdlTypeLayout {
+    pub serialization: IdlSerialization,
+    pub repr: Option<IdlRepr>,
+}
+
+impl IdlTypeLayout {
+    /// Options of the struct with this layout: types serialized with bytemuck are zero
+    /// copy, with the packing and alignment of their representation.
+    pub fn struct_opts(&self) -> StructOpts {
+        if self.serialization == IdlSerialization::Borsh {
+            return StructOpts::default();
+        }
+        let (packed, align) = match self.repr {
+            Some(IdlRepr::Rust { packed, align }) | Some(IdlRepr::C { packed, align }) => {
+                (packed, align)
+            }
+            Some(IdlRepr::Transparent) | None => (false, None),
+        };
+        StructOpts {
+            packed,
+            zero_copy: true,
+            align,
+            ..Default::default()
+        }
+    }
+}
+
+/// Information from Anchor 0.30+ IDLs which does not fit into the legacy [Idl] model.
+///
+/// Empty for legacy IDLs.
+#[derive(Clone, Debug, Default)]
+pub struct IdlExtensions {
+    /// Address of the program.
+    pub address: Option<String>,
+    /// Instruction discriminators, keyed by instruction name.
+    pub instruction_discriminators: BTreeMap<String, Vec<u8>>,
+    /// Account discriminators, keyed by account name.
+    pub account_discriminators: BTreeMap<String, Vec<u8>>,
+    /// Event discriminators, keyed by event name.
+    pub event_discriminators: BTreeMap<String, Vec<u8>>,
+    /// Fixed addresses of instruction accounts, keyed by instruction name and account path.
+    pub account_addresses: BTreeMap<(String, String), String>,
+    /// Serialization and representation of type definitions, keyed by type name.
+    pub type_layouts: BTreeMap<String, IdlTypeLayout>,
+}
+
+impl IdlExtensions {
+    /// Discriminator of an instruction.
+    pub fn instruction_discriminator(&self, ix_name: &str) -> Vec<u8> {
+        self.instruction_discriminators
+            .get(ix_name)
+            .cloned()
+            .unwrap_or_else(|| sighash("global", &ix_name.to_snake_case()).to_vec())
+    }
+
+    /// Discriminator of an account.
+    pub fn account_discriminator(&self, account_name: &str) -> Vec<u8> {
+        self.account_discriminators
+            .get(account_name)
+            .cloned()
+            .unwrap_or_else(|| sighash("account", account_name).to_vec())
+    }
+
+    /// Discriminator of an event.
+    pub fn event_discriminator(&self, event_name: &str) -> Vec<u8> {
+        self.event_discriminators
+            .get(event_name)
+            .cloned()
+            .unwrap_or_else(|| sighash("event", event_name).to_vec())
+    }
+}
+
+/// Computes an Anchor sighash, i.e. the first 8 bytes of `sha256("<namespace>:<name>")`.
+pub fn sighash(namespace: &str, name: &str) -> [u8; 8] {
+    let mut hasher = Sha256::new();
+    hasher.update(format!("{}:{}", namespace, name).as_bytes());
+    let hash = hasher.finalize();
+
+    let mut discriminator = [0u8; 8];
+    discriminator.copy_from_slice(&hash[..8]);
+    discriminator
+}
+
+/// Parses an IDL in either the legacy or the Anchor 0.30+ specification.
+pub fn parse_idl(contents: &str) -> serde_json::Result<(Idl, IdlExtensions)> {
+    let value: serde_json::Value = serde_json::from_str(contents)?;
+    let is_new_spec = value.get("address").is_some()
+        && value
+            .get("metadata")
+            .and_then(|metadata| metadata.get("spec"))
+            .is_some();
+    if is_new_spec {
+        let idl: spec::Idl = serde_json::from_value(value)?;
+        convert_idl(idl)
+    } else {
+        Ok((serde_json::from_value(value)?, IdlExtensions::default()))
+    }
+}
+
+/// Converts an Anchor 0.30+ IDL into the legacy model.
+fn convert_idl(idl: spec::Idl) -> serde_json::Result<(Idl, IdlExtensions)> {
+    let mut ext = IdlExtensions {
+        address: Some(idl.address.clone()),
+        ..Default::default()
+    };
+
+    let mut types = idl
+        .types
+        .iter()
+        .map(|def| {
+            ext.type_layouts
+                .insert(def.name.clone(), convert_type_layout(def)?);
+            convert_typedef(def)
+        })
+        .collect::<serde_json::Result<Vec<_>>>()?;
+
+    // Account and event layouts are hosted in `types`; the legacy model keeps them apart.
+    let mut take_typedef = |name: &str| {
+        types
+            .iter()
+            .position(|def| def.name == name)
+            .map(|index| types.remove(index))
+            .ok_or_else(|| serde_json::Error::custom(format!("type `{}` not found", name)))
+    };
+
+    let accounts = idl
+        .accounts
+        .iter()
+        .map(|account| {
+            ext.account_discriminators
+                .insert(account.name.clone(), account.discriminator.clone());
+            take_typedef(&account.name)
+        })
+        .collect::<serde_json::Result<Vec<_>>>()?;
+
+    let events = idl
+        .events
+        .iter()
+        .map(|event| {
+            ext.event_discriminators
+                .insert(event.name.clone(), event.discriminator.clone());
+            let def = take_typedef(&event.name)?;
+            let fields = match def.ty {
+                IdlTypeDefinitionTy::Struct { fields } => fields,
+                _ => {
+                    return Err(serde_json::Error::custom(format!(
+                        "event `{}` is not a struct",
+                        event.name
+                    )))
+                }
+            };
+            Ok(IdlEvent {
+                name: event.name.clone(),
+                fields: fields
+                    .into_iter()
+                    .map(|field| IdlEventField {
+                        name: field.name,
+                        ty: field.ty,
+                        index: false,
+                    })
+                    .collect(),
+            })
+        })
+        .collect::<serde_json::Result<Vec<_>>>()?;
+
+    let instructions = idl
+        .instructions
+        .iter()
+        .map(|ix| {
+            ext.instruction_discriminators
+                .insert(ix.name.clone(), ix.discriminator.clone());
+            convert_instruction(ix, &idl, &mut ext)
+        })
+        .collect::<serde_json::Result<Vec<_>>>()?;
+
+    let constants = idl
+        .constants
+        .iter()
+        .map(|constant| {
+            Ok(IdlConst {
+                name: constant.name.clone(),
+                ty: convert_type(&constant.ty)?,
+                value: constant.value.clone(),
+            })
+        })
+        .collect::<serde_json::Result<Vec<_>>>()?;
+
+    let errors = idl
+        .errors
+        .iter()
+        .map(|error| IdlErrorCode {
+            code: error.code,
+            name: error.name.clone(),
+            msg: error.msg.clone(),
+        })
+        .collect::<Vec<_>>();
+
+    let mut metadata = serde_json::to_value(&idl.metadata)?;
+    if let serde_json::Value::Object(map) = &mut metadata {
+        map.insert(
+            "address".to_string(),
+            serde_json::Value::String(idl.address.clone()),
+        );
+    }
+
+    Ok((
+        Idl {
+            version: idl.metadata.version.clone(),
+            name: idl.metadata.name.clone(),
+            docs: convert_docs(&idl.docs),
+            constants,
+            instructions,
+            accounts,
+            types,
+            events: (!events.is_empty()).then_some(events),
+            errors: (!errors.is_empty()).then_some(errors),
+            metadata: Some(metadata),
+        },
+        ext,
+    ))
+}
+
+fn convert_docs(docs: &[String]) -> Option<Vec<String>> {
+    (!docs.is_empty()).then(|| docs.to_vec())
+}
+
+fn convert_type_layout(def: &spec::IdlTypeDef) -> serde_json::Result<IdlTypeLayout> {
+    let serialization = match &def.serialization {
+        spec::IdlSerialization::Borsh => IdlSerialization::Borsh,
+        spec::IdlSerialization::Bytemuck => IdlSerialization::Bytemuck,
+        // `#[zero_copy(unsafe)]` types may have padding, so they cannot be generated as Pod.
+        spec::IdlSerialization::BytemuckUnsafe => {
+            return Err(serde_json::Error::custom(format!(
+                "unsupported serialization {:?} of type `{}`, which is not plain old data",
+                def.serialization, def.name
+            )))
+        }
+        other => {
+            return Err(serde_json::Error::custom(format!(
+                "unsupported serialization {:?} of type `{}`",
+                other, def.name
+            )))
+        }
+    };
+    let repr = match &def.repr {
+        None => None,
+        Some(spec::IdlRepr::Rust(modifier)) => Some(IdlRepr::Rust {
+            packed: modifier.packed,
+            align: modifier.align,
+        }),
+        Some(spec::IdlRepr::C(modifier)) => Some(IdlRepr::C {
+            packed: modifier.packed,
+            align: modifier.align,
+        }),
+        Some(spec::IdlRepr::Transparent) => Some(IdlRepr::Transparent),
+        Some(other) => {
+            return Err(serde_json::Error::custom(format!(
+                "unsupported repr {:?} of type `{}`",
+                other, def.name
+            )))
+        }
+    };
+    Ok(IdlTypeLayout {
+        serialization,
+        repr,
+    })
+}
+
+fn convert_typedef(def: &spec::IdlTypeDef) -> serde_json::Result<IdlTypeDefinition> {
+    let ty = match &def.ty {
+        spec::IdlTypeDefTy::Struct { fields } => IdlTypeDefinitionTy::Struct {
+            fields: convert_struct_fields(fields.as_ref())?,
+        },
+        spec::IdlTypeDefTy::Enum { variants } => IdlTypeDefinitionTy::Enum {
+            variants: variants
+                .iter()
+                .map(|variant| {
+                    Ok(anchor_syn::idl::types::IdlEnumVariant {
+                        name: variant.name.clone(),
+                        fields: match &variant.fields {
+                            None => None,
+                            Some(spec::IdlDefinedFields::Named(fields)) => {
+                                Some(EnumFields::Named(convert_fields(fields)?))
+                            }
+                            Some(spec::IdlDefinedFields::Tuple(types)) => Some(EnumFields::Tuple(
+                                types
+                                    .iter()
+                                    .map(convert_type)
+                                    .collect::<serde_json::Result<_>>()?,
+                            )),
+                        },
+                    })
+                })
+                .collect::<serde_json::Result<_>>()?,
+        },
+        spec::IdlTypeDefTy::Type { alias } => IdlTypeDefinitionTy::Alias {
+            value: convert_type(alias)?,
+        },
+    };
+    let generics = def
+        .generics
+        .iter()
+        .map(|generic| match generic {
+            spec::IdlTypeDefGeneric::Type { name } => name.clone(),
+            spec::IdlTypeDefGeneric::Const { name, .. } => name.clone(),
+        })
+        .collect::<Vec<_>>();
+    Ok(IdlTypeDefinition {
+        name: def.name.clone(),
+        docs: convert_docs(&def.docs),
+        generics: (!generics.is_empty()).then_some(generics),
+        ty,
+    })
+}
+
+/// Tuple struct fields are named by their position, i.e. `0`, `1`, ...
+fn convert_struct_fields(
+    fields: Option<&spec::IdlDefinedFields>,
+) -> serde_json::Result<Vec<IdlField>> {
+    match fields {
+        None => Ok(vec![]),
+        Some(spec::IdlDefinedFields::Named(fields)) => convert_fields(fields),
+        Some(spec::IdlDefinedFields::Tuple(types)) => types
+            .iter()
+            .enumerate()
+            .map(|(index, ty)| {
+                Ok(IdlField {
+                    name: index.to_string(),
+                    docs: None,
+                    ty: convert_type(ty)?,
+                })
+            })
+            .collect(),
+    }
+}
+
+fn convert_fields(fields: &[spec::IdlField]) -> serde_json::Result<Vec<IdlField>> {
+    fields
+        .iter()
+        .map(|field| {
+            Ok(IdlField {
+                name: field.name.clone(),
+                docs: convert_docs(&field.docs),
+                ty: convert_type(&field.ty)?,
+            })
+        })
+        .collect()
+}
+
+fn convert_type(ty: &spec::IdlType) -> serde_json::Result<IdlType> {
+    Ok(match ty {
+        spec::IdlType::Bool => IdlType::Bool,
+        spec::IdlType::U8 => IdlType::U8,
+        spec::IdlType::I8 => IdlType::I8,
+        spec::IdlType::U16 => IdlType::U16,
+        spec::IdlType::I16 => IdlType::I16,
+        spec::IdlType::U32 => IdlType::U32,
+        spec::IdlType::I32 => IdlType::I32,
+        spec::IdlType::F32 => IdlType::F32,
+        spec::IdlType::U64 => IdlType::U64,
+        spec::IdlType::I64 => IdlType::I64,
+        spec::IdlType::F64 => IdlType::F64,
+        spec::IdlType::U128 => IdlType::U128,
+        spec::IdlType::I128 => IdlType::I128,
+        spec::IdlType::U256 => IdlType::U256,
+        spec::IdlType::I256 => IdlType::I256,
+        spec::IdlType::Bytes => IdlType::Bytes,
+        spec::IdlType::String => IdlType::String,
+        spec::IdlType::Pubkey => IdlType::PublicKey,
+        spec::IdlType::Option(inner) => IdlType::Option(Box::new(convert_type(inner)?)),
+        spec::IdlType::Vec(inner) => IdlType::Vec(Box::new(convert_type(inner)?)),
+        spec::IdlType::Array(inner, spec::IdlArrayLen::Value(len)) => {
+            IdlType::Array(Box::new(convert_type(inner)?), *len)
+        }
+        spec::IdlType::Array(inner, spec::IdlArrayLen::Generic(len)) => {
+            IdlType::GenericLenArray(Box::new(convert_type(inner)?), len.clone())
+        }
+        spec::IdlType::Defined { name, generics } if generics.is_empty() => {
+            IdlType::Defined(name.clone())
+        }
+        spec::IdlType::Defined { name, generics } => IdlType::DefinedWithTypeArgs {
+            name: name.clone(),
+            args: generics
+                .iter()
+                .map(|arg| {
+                    Ok(match arg {
+                        spec::IdlGenericArg::Type {
+                            ty: spec::IdlType::Generic(generic),
+                        } => IdlDefinedTypeArg::Generic(generic.clone()),
+                        spec::IdlGenericArg::Type { ty } => {
+                            IdlDefinedTypeArg::Type(convert_type(ty)?)
+                        }
+                        spec::IdlGenericArg::Const { value } => {
+                            IdlDefinedTypeArg::Value(value.clone())
+                        }
+                    })
+                })
+                .collect::<serde_json::Result<_>>()?,
+        },
+        spec::IdlType::Generic(name) => IdlType::Generic(name.clone()),
+        other => {
+            return Err(serde_json::Error::custom(format!(
+                "unsupported type {:?}",
+                other
+            )))
+        }
+    })
+}
+
+fn convert_instruction(
+    ix: &spec::IdlInstruction,
+    idl: &spec::Idl,
+    ext: &mut IdlExtensions,
+) -> serde_json::Result<IdlInstruction> {
+    Ok(IdlInstruction {
+        name: ix.name.clone(),
+        docs: convert_docs(&ix.docs),
+        accounts: convert_account_items(&ix.accounts, ix, idl, "", ext)?,
+        args: convert_fields(&ix.args)?,
+        returns: ix.returns.as_ref().map(convert_type).transpose()?,
+    })
+}
+
+fn convert_account_items(
+    items: &[spec::IdlInstructionAccountItem],
+    ix: &spec::IdlInstruction,
+    idl: &spec::Idl,
+    prefix: &str,
+    ext: &mut IdlExtensions,
+) -> serde_json::Result<Vec<IdlAccountItem>> {
+    items
+        .iter()
+        .map(|item| {
+            Ok(match item {
+                spec::IdlInstructionAccountItem::Single(account) => {
+                    let path = format!("{}{}", prefix, account.name);
+                    if let Some(address) = &account.address {
+                        ext.account_addresses
+                            .insert((ix.name.clone(), path), address.clone());
+                    }
+                    IdlAccountItem::IdlAccount(IdlAccount {
+                        name: account.name.clone(),
+                        is_mut: account.writable,
+                        is_signer: account.signer,
+                        is_optional: account.optional.then_some(true),
+                        docs: convert_docs(&account.docs),
+                        pda: account
+                            .pda
+                            .as_ref()
+                            .map(|pda| convert_pda(pda, ix, idl))
+                            .transpose()?,
+                        relations: account.relations.clone(),
+                    })
+                }
+                spec::IdlInstructionAccountItem::Composite(accounts) => {
+                    let prefix = format!("{}{}.", prefix, accounts.name);
+                    IdlAccountItem::IdlAccounts(IdlAccounts {
+                        name: accounts.name.clone(),
+                        accounts: convert_account_items(&accounts.accounts, ix, idl, &prefix, ext)?,
+                    })
+                }
+            })
+        })
+        .collect()
+}
+
+fn convert_pda(
+    pda: &spec::IdlPda,
+    ix: &spec::IdlInstruction,
+    idl: &spec::Idl,
+) -> serde_json::Result<IdlPda> {
+    Ok(IdlPda {
+        seeds: pda
+            .seeds
+            .iter()
+            .map(|seed| convert_seed(seed, ix, idl))
+            .collect::<serde_json::Result<_>>()?,
+        program_id: pda
+            .program
+            .as_ref()
+            .map(|seed| convert_seed(seed, ix, idl))
+            .transpose()?,
+    })
+}
+
+/// Legacy seeds carry their type, which Anchor 0.30+ IDLs leave to be resolved from the path.
+fn convert_seed(
+    seed: &spec::IdlSeed,
+    ix: &spec::IdlInstruction,
+    idl: &spec::Idl,
+) -> serde_json::Result<IdlSeed> {
+    Ok(match seed {
+        spec::IdlSeed::Const(seed) => IdlSeed::Const(IdlSeedConst {
+            ty: IdlType::Bytes,
+            value: serde_json::to_value(&seed.value)?,
+        }),
+        spec::IdlSeed::Arg(seed) => {
+            let mut segments = seed.path.split('.');
+            let arg_name = segments.next().unwrap_or_default();
+            let arg = ix
+                .args
+                .iter()
+                .find(|arg| arg.name == arg_name)
+                .ok_or_else(|| {
+                    serde_json::Error::custom(format!(
+                        "seed arg `{}` not found in instruction `{}`",
+                        seed.path, ix.name
+                    ))
+                })?;
+            IdlSeed::Arg(IdlSeedArg {
+                ty: resolve_path_type(&arg.ty, segments, idl)?,
+                path: seed.path.clone(),
+            })
+        }
+        spec::IdlSeed::Account(seed) => {
+            // Paths either point at the key of an account, possibly nested, or at a field of
+            // the data of an account.
+            let ty = match (&seed.account, seed.path.split_once('.')) {
+                _ if has_account_path(&ix.accounts, &seed.path) => IdlType::PublicKey,
+                (Some(account), Some((_, fields))) => resolve_path_type(
+                    &spec::IdlType::Defined {
+                        name: account.clone(),
+                        generics: vec![],
+                    },
+                    fields.split('.'),
+                    idl,
+                )?,
+                _ => IdlType::PublicKey,
+            };
+            IdlSeed::Account(IdlSeedAccount {
+                ty,
+                account: seed.account.clone(),
+                path: seed.path.clone(),
+            })
+        }
+    })
+}
+
+/// Checks whether `path` points at an account of an instruction, e.g. `group.account`.
+fn has_account_path(items: &[spec::IdlInstructionAccountItem], path: &str) -> bool {
+    items.iter().any(|item| match item {
+        spec::IdlInstructionAccountItem::Single(account) => account.name == path,
+        spec::IdlInstructionAccountItem::Composite(accounts) => path
+            .strip_prefix(accounts.name.as_str())
+            .and_then(|rest| rest.strip_prefix('.'))
+            .is_some_and(|rest| has_account_path(&accounts.accounts, rest)),
+    })
+}
+
+/// Resolves the type of a field reached by following `segments` from `ty`.
+fn resolve_path_type<'a>(
+    ty: &spec::IdlType,
+    mut segments: impl Iterator<Item = &'a str>,
+    idl: &spec::Idl,
+) -> serde_json::Result<IdlType> {
+    let Some(segment) = segments.next() else {
+        return convert_type(ty);
+    };
+    let field = match ty {
+        spec::IdlType::Defined { name, .. } => idl
+            .types
+            .iter()
+            .find(|def| def.name == *name)
+            .and_then(|def| match &def.ty {
+                spec::IdlTypeDefTy::Struct {
+                    fields: Some(spec::IdlDefinedFields::Named(fields)),
+                } => fields.iter().find(|field| field.name == segment),
+                _ => None,
+            }),
+        _ => None,
+    };
+    let field = field.ok_or_else(|| {
+        serde_json::Error::custom(format!("seed path segment `{}` not found", segment))
+    })?;
+    resolve_path_type(&field.ty, segments, idl)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const IDL: &str = r#"{
+        "address": "whirLbMiicVdio4qvUfM5KAg6Ct8VwpYzGff3uctyCc",
+        "metadata": { "name": "test", "version": "0.1.0", "spec": "0.1.0" },
+        "instructions": [
+            {
+                "name": "open_position",
+                "discriminator": [1, 2, 3, 4, 5, 6, 7, 8],
+                "accounts": [
+                    { "name": "owner", "writable": true, "signer": true },
+                    {
+                        "name": "position",
+                        "writable": true,
+                        "pda": {
+                            "seeds": [
+                                { "kind": "const", "value": [112, 111, 115] },
+                                { "kind": "arg", "path": "params.index" },
+                                { "kind": "account", "path": "owner" }
+                            ]
+                        }
+                    },
+                    {
+                        "name": "programs",
+                        "accounts": [
+                            {
+                                "name": "system_program",
+                                "address": "11111111111111111111111111111111"
+                            }
+                        ]
+                    }
+                ],
+                "args": [
+                    { "name": "params", "type": { "defined": { "name": "Params" } } }
+                ]
+            }
+        ],
+        "accounts": [{ "name": "Position", "discriminator": [9, 9, 9, 9, 9, 9, 9, 9] }],
+        "events": [{ "name": "Opened", "discriminator": [7, 7, 7, 7, 7, 7, 7, 7] }],
+        "types": [
+            {
+                "name": "Params",
+                "type": {
+                    "kind": "struct",
+                    "fields": [
+                        { "name": "index", "type": "u16" },
+                        {
+                            "name": "pair",
+                            "type": {
+                                "defined": {
+                                    "name": "Pair",
+                                    "generics": [
+                                        { "kind": "type", "type": "pubkey" },
+                                        { "kind": "const", "value": "4" }
+                                    ]
+                                }
+                            }
+                        }
+                    ]
+                }
+            },
+            {
+                "name": "Pair",
+                "generics": [
+                    { "kind": "type", "name": "T" },
+                    { "kind": "const", "name": "N", "type": "usize" }
+                ],
+                "type": {
+                    "kind": "struct",
+                    "fields": [{ "generic": "T" }, { "array": ["u8", { "generic": "N" }] }]
+                }
+            },
+            {
+                "name": "Position",
+                "serialization": "bytemuck",
+                "repr": { "kind": "c", "packed": true },
+                "type": { "kind": "struct", "fields": [{ "name": "liquidity", "type": "u128" }] }
+            },
+            {
+                "name": "Opened",
+                "type": { "kind": "struct", "fields": [{ "name": "index", "type": "u16" }] }
+            }
+        ]
+    }"#;
+
+    fn find_type<'a>(idl: &'a Idl, name: &str) -> &'a IdlTypeDefinition {
+        idl.types
+            .iter()
+            .chain(&idl.accounts)
+            .find(|def| def.name == name)
+            .unwrap()
+    }
+
+    fn struct_fields(def: &IdlTypeDefinition) -> &[IdlField] {
+        match &def.ty {
+            IdlTypeDefinitionTy::Struct { fields } => fields,
+            _ => panic!("`{}` is not a struct", def.name),
+        }
+    }
+
+    #[test]
+    fn parse_legacy_idl() {
+        let (idl, ext) = parse_idl(
+            r#"{
+                "version": "0.1.0",
+                "name": "legacy",
+                "instructions": [{ "name": "init", "accounts": [], "args": [] }],
+                "metadata": { "address": "whirLbMiicVdio4qvUfM5KAg6Ct8VwpYzGff3uctyCc" }
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(idl.name, "legacy");
+        assert_eq!(idl.instructions[0].name, "init");
+        assert!(ext.address.is_none());
+        assert!(ext.instruction_discriminators.is_empty());
+    }
+
+    #[test]
+    fn parse_new_idl_discriminators() {
+        let (idl, ext) = parse_idl(IDL).unwrap();
+        assert_eq!(idl.name, "test");
+        assert_eq!(
+            ext.address.as_deref(),
+            Some("whirLbMiicVdio4qvUfM5KAg6Ct8VwpYzGff3uctyCc")
+        );
+        assert_eq!(
+            ext.instruction_discriminator("open_position"),
+            [1, 2, 3, 4, 5, 6, 7, 8]
+        );
+        assert_eq!(ext.account_discriminator("Position"), [9; 8]);
+        assert_eq!(ext.event_discriminators["Opened"], [7; 8]);
+    }
+
+    #[test]
+    fn parse_new_idl_moves_accounts_and_events_out_of_types() {
+        let (idl, _) = parse_idl(IDL).unwrap();
+        let type_names: Vec<&str> = idl.types.iter().map(|def| def.name.as_str()).collect();
+        assert_eq!(type_names, ["Params", "Pair"]);
+        assert_eq!(idl.accounts[0].name, "Position");
+        let events = idl.events.unwrap();
+        assert_eq!(events[0].name, "Opened");
+        assert_eq!(events[0].fields[0].ty, IdlType::U16);
+    }
+
+    #[test]
+    fn parse_new_idl_types() {
+        let (idl, _) = parse_idl(IDL).unwrap();
+        let pair = find_type(&idl, "Pair");
+        assert_eq!(
+            pair.generics.as_deref(),
+            Some(&["T".to_string(), "N".to_string()][..])
+        );
+        let fields = struct_fields(pair);
+        assert_eq!(fields[0].name, "0");
+        assert_eq!(fields[0].ty, IdlType::Generic("T".to_string()));
+        assert_eq!(
+            fields[1].ty,
+            IdlType::GenericLenArray(Box::new(IdlType::U8), "N".to_string())
+        );
+        assert_eq!(
+            struct_fields(find_type(&idl, "Params"))[1].ty,
+            IdlType::DefinedWithTypeArgs {
+                name: "Pair".to_string(),
+                args: vec![
+                    IdlDefinedTypeArg::Type(IdlType::PublicKey),
+                    IdlDefinedTypeArg::Value("4".to_string()),
+                ],
+            }
+        );
+    }
+
+    #[test]
+    fn parse_new_idl_layouts() {
+        let (_, ext) = parse_idl(IDL).unwrap();
+        let opts = ext.type_layouts["Position"].struct_opts();
+        assert!(opts.zero_copy && opts.packed);
+        assert_eq!(opts.align, None);
+        assert!(!ext.type_layouts["Params"].struct_opts().zero_copy);
+    }
+
+    #[test]
+    fn parse_new_idl_accounts() {
+        let (idl, ext) = parse_idl(IDL).unwrap();
+        assert_eq!(
+            ext.account_addresses[&(
+                "open_position".to_string(),
+                "programs.system_program".to_string()
+            )],
+            "11111111111111111111111111111111"
+        );
+        let position = match &idl.instructions[0].accounts[1] {
+            IdlAccountItem::IdlAccount(account) => account,
+            _ => panic!("expected a single account"),
+        };
+        let seeds = &position.pda.as_ref().unwrap().seeds;
+        assert!(matches!(&seeds[0], IdlSeed::Const(seed) if seed.ty == IdlType::Bytes));
+        // Seed types are resolved from the paths.
+        assert!(matches!(&seeds[1], IdlSeed::Arg(seed) if seed.ty == IdlType::U16));
+        assert!(matches!(&seeds[2], IdlSeed::Account(seed) if seed.ty == IdlType::PublicKey));
+    }
+
+    #[test]
+    fn parse_new_idl_rejects_bytemuck_unsafe() {
+        let idl = IDL.replace(r#""bytemuck""#, r#""bytemuckunsafe""#);
+        let err = parse_idl(&idl).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("unsupported serialization BytemuckUnsafe of type `Position`"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn parse_new_idl_requires_account_types() {
+        let idl = IDL.replace(
+            r#"{ "name": "Position", "discriminator""#,
+            r#"{ "name": "Missing", "discriminator""#,
+        );
+        let err = parse_idl(&idl).unwrap_err();
+        assert!(
+            err.to_string().contains("type `Missing` not found"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn custom_discriminators() {
+        let (mut idl, ext) = parse_idl(IDL).unwrap();
+        crate::validate_idl(&idl, &ext).unwrap();
+        // `#[program]` cannot generate the CPI of instructions with custom discriminators.
+        match crate::validate_instruction_discriminators(&idl, &ext) {
+            Err(crate::GeneratorError::CustomDiscriminator { name, .. }) => {
+                assert_eq!(name, "open_position")
+            }
+            _ => panic!("expected a custom discriminator error"),
+        }
+        idl.instructions.clear();
+        crate::validate_instruction_discriminators(&idl, &ext).unwrap();
+
+        let (idl, ext) = parse_idl(&IDL.replace("[9, 9, 9, 9, 9, 9, 9, 9]", "[9, 9]")).unwrap();
+        match crate::validate_idl(&idl, &ext) {
+            Err(crate::GeneratorError::InvalidDiscriminatorLength { kind, name, .. }) => {
+                assert_eq!((kind, name.as_str()), ("account", "Position"))
+            }
+            _ => panic!("expected a discriminator length error"),
+        }
+    }
+
+    #[test]
+    fn sighash_matches_anchor() {
+        // Discriminator of the `initialize` instruction of every Anchor program.
+        assert_eq!(
+            sighash("global", "initialize"),
+            [175, 175, 109, 31, 13, 152, 155, 237]
+        );
+    }
+}
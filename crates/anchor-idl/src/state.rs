@@ -1,17 +1,118 @@
 use std::collections::BTreeMap;
 
-use anchor_syn::idl::types::{IdlField, IdlTypeDefinition};
-use proc_macro2::TokenStream;
+use anchor_syn::idl::types::{IdlEnumVariant, IdlField, IdlTypeDefinition};
+use proc_macro2::{Ident, TokenStream};
 use quote::{format_ident, quote};
 
-use crate::{generate_fields, get_field_list_properties, StructOpts};
+use crate::{
+    generate_enum, generate_struct_body, get_field_list_properties, GeneratorError, IdlExtensions,
+    StructOpts,
+};
+
+/// Generates the docs of an account, which default to its name.
+fn generate_account_docs(account_name: &str, docs: Option<&[String]>) -> TokenStream {
+    match docs {
+        Some(docs) if !docs.is_empty() => crate::generate_docs(Some(docs)),
+        _ => {
+            let doc = format!(" Account: {}", account_name);
+            quote! {
+                #[doc = #doc]
+            }
+        }
+    }
+}
+
+/// Generates the traits `#[account]` implements, for the accounts it cannot generate, i.e.
+/// enums and accounts with a custom discriminator.
+fn generate_account_impls(name: &Ident, discriminator: &[u8], zero_copy: bool) -> TokenStream {
+    let serialize = if zero_copy {
+        quote! {
+            #[automatically_derived]
+            unsafe impl anchor_lang::__private::bytemuck::Pod for #name {}
+
+            #[automatically_derived]
+            unsafe impl anchor_lang::__private::bytemuck::Zeroable for #name {}
+
+            #[automatically_derived]
+            impl anchor_lang::ZeroCopy for #name {}
+        }
+    } else {
+        quote! {
+            #[automatically_derived]
+            impl anchor_lang::AccountSerialize for #name {
+                fn try_serialize<W: std::io::Write>(&self, writer: &mut W) -> anchor_lang::Result<()> {
+                    if writer.write_all(&[#(#discriminator),*]).is_err() {
+                        return Err(anchor_lang::error::ErrorCode::AccountDidNotSerialize.into());
+                    }
+                    if AnchorSerialize::serialize(self, writer).is_err() {
+                        return Err(anchor_lang::error::ErrorCode::AccountDidNotSerialize.into());
+                    }
+                    Ok(())
+                }
+            }
+        }
+    };
+    let deserialize_unchecked = if zero_copy {
+        quote! {
+            let data: &[u8] = &buf[8..];
+            let account = anchor_lang::__private::bytemuck::from_bytes(data);
+            Ok(*account)
+        }
+    } else {
+        quote! {
+            let mut data: &[u8] = &buf[8..];
+            AnchorDeserialize::deserialize(&mut data)
+                .map_err(|_| anchor_lang::error::ErrorCode::AccountDidNotDeserialize.into())
+        }
+    };
+    quote! {
+        #serialize
+
+        #[automatically_derived]
+        impl anchor_lang::AccountDeserialize for #name {
+            fn try_deserialize(buf: &mut &[u8]) -> anchor_lang::Result<Self> {
+                if buf.len() < 8 {
+                    return Err(anchor_lang::error::ErrorCode::AccountDiscriminatorNotFound.into());
+                }
+                if buf[..8] != [#(#discriminator),*] {
+                    return Err(anchor_lang::error::ErrorCode::AccountDiscriminatorMismatch.into());
+                }
+                Self::try_deserialize_unchecked(buf)
+            }
+
+            fn try_deserialize_unchecked(buf: &mut &[u8]) -> anchor_lang::Result<Self> {
+                #deserialize_unchecked
+            }
+        }
+
+        #[automatically_derived]
+        impl anchor_lang::Discriminator for #name {
+            fn discriminator() -> [u8; 8] {
+                [#(#discriminator),*]
+            }
+        }
+
+        #[automatically_derived]
+        impl anchor_lang::Owner for #name {
+            fn owner() -> Pubkey {
+                crate::ID
+            }
+        }
+    }
+}
 
 /// Generates an account state struct.
+///
+/// Accounts with a custom discriminator implement the account traits by hand, as `#[account]`
+/// derives the discriminator from the name.
 pub fn generate_account(
     defs: &[IdlTypeDefinition],
     account_name: &str,
+    docs: Option<&[String]>,
     fields: &[IdlField],
     opts: StructOpts,
+    attrs: &TokenStream,
+    extensions: &IdlExtensions,
 ) -> TokenStream {
     let props = get_field_list_properties(defs, fields);
 
@@ -29,59 +130,121 @@ pub fn generate_account(
     } else {
         quote! {}
     };
-    let derive_account = if opts.zero_copy {
-        let repr = if opts.packed {
-            quote! {
-                #[repr(packed)]
-            }
-        } else {
-            quote! {
-                #[repr(C)]
-            }
-        };
-        quote! {
-            #[account(zero_copy)]
-            #repr
-        }
+    let struct_name = format_ident!("{}", account_name);
+    let discriminator = extensions.account_discriminator(account_name);
+    let is_custom = discriminator != crate::sighash("account", account_name);
+    let repr = if opts.zero_copy {
+        crate::generate_zero_copy_repr(opts)
     } else {
-        quote! {
-            #[account]
-        }
+        quote! {}
+    };
+    let (derive_account, impls) = match (is_custom, opts.zero_copy) {
+        (false, false) => (quote! { #[account] }, quote! {}),
+        (false, true) => (quote! { #[account(zero_copy)] #repr }, quote! {}),
+        (true, false) => (
+            quote! { #[derive(AnchorSerialize, AnchorDeserialize, Clone)] },
+            generate_account_impls(&struct_name, &discriminator, false),
+        ),
+        (true, true) => (
+            quote! { #[zero_copy] #repr },
+            generate_account_impls(&struct_name, &discriminator, true),
+        ),
     };
 
-    let doc = format!(" Account: {}", account_name);
-    let struct_name = format_ident!("{}", account_name);
-    let fields_rendered = generate_fields(fields);
+    let docs = generate_account_docs(account_name, docs);
+    let body = generate_struct_body(
+        defs,
+        account_name,
+        &crate::get_generated_fields(fields, opts),
+    );
     quote! {
         #derive_account
-        #[doc = #doc]
+        #docs
+        #[derive(Debug)]
         #derive_copy
         #derive_default
-        pub struct #struct_name {
-            #fields_rendered
-        }
+        #attrs
+        pub struct #struct_name #body
+
+        #impls
     }
 }
 
-/// Generates account state structs.
+/// Generates an account state enum.
+///
+/// `#[account]` only supports structs, so the account traits are implemented like it would.
+pub fn generate_enum_account(
+    defs: &[IdlTypeDefinition],
+    account_name: &str,
+    docs: Option<&[String]>,
+    variants: &[IdlEnumVariant],
+    extensions: &IdlExtensions,
+) -> TokenStream {
+    let docs = generate_account_docs(account_name, docs);
+    let enum_name = format_ident!("{}", account_name);
+    let item = generate_enum(defs, &enum_name, &[], variants);
+    let discriminator = extensions.account_discriminator(account_name);
+    let impls = generate_account_impls(&enum_name, &discriminator, false);
+    quote! {
+        #docs
+        #item
+
+        #impls
+    }
+}
+
+/// Generates account state structs and enums.
+///
+/// `type_attrs` are the extra attributes of accounts, keyed by name.
 pub fn generate_accounts(
     typedefs: &[IdlTypeDefinition],
     account_defs: &[IdlTypeDefinition],
     struct_opts: &BTreeMap<String, StructOpts>,
-) -> TokenStream {
-    let defined = account_defs.iter().map(|def| match &def.ty {
-        anchor_syn::idl::types::IdlTypeDefinitionTy::Struct { fields } => {
-            let opts = struct_opts.get(&def.name).copied().unwrap_or_default();
-            generate_account(typedefs, &def.name, fields, opts)
-        }
-        anchor_syn::idl::types::IdlTypeDefinitionTy::Enum { .. } => {
-            panic!("unexpected enum account");
-        }
-        _ => {
-            todo!()
+    type_attrs: &BTreeMap<String, TokenStream>,
+    extensions: &IdlExtensions,
+) -> Result<TokenStream, GeneratorError> {
+    let defined = account_defs.iter().map(|def| {
+        let attrs = type_attrs.get(&def.name).cloned().unwrap_or_default();
+        match &def.ty {
+            anchor_syn::idl::types::IdlTypeDefinitionTy::Struct { fields } => {
+                let opts = struct_opts.get(&def.name).copied().unwrap_or_default();
+                let item = generate_account(
+                    typedefs,
+                    &def.name,
+                    def.docs.as_deref(),
+                    fields,
+                    opts,
+                    &attrs,
+                    extensions,
+                );
+                let assertions = crate::generate_layout_assertions(typedefs, struct_opts, def);
+                Ok(quote! {
+                    #item
+                    #assertions
+                })
+            }
+            anchor_syn::idl::types::IdlTypeDefinitionTy::Enum { variants } => {
+                let item = generate_enum_account(
+                    typedefs,
+                    &def.name,
+                    def.docs.as_deref(),
+                    variants,
+                    extensions,
+                );
+                Ok(quote! {
+                    #attrs
+                    #item
+                })
+            }
+            anchor_syn::idl::types::IdlTypeDefinitionTy::Alias { .. } => {
+                Err(GeneratorError::AliasAccount {
+                    name: def.name.clone(),
+                })
+            }
         }
     });
-    quote! {
+    let defined = defined.collect::<Result<Vec<_>, GeneratorError>>()?;
+    Ok(quote! {
         #(#defined)*
-    }
+    })
 }
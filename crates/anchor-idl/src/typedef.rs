@@ -1,6 +1,9 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 
-use anchor_syn::idl::types::{EnumFields, IdlEnumVariant, IdlField, IdlType, IdlTypeDefinition};
+use anchor_syn::idl::types::{
+    EnumFields, Idl, IdlDefinedTypeArg, IdlEnumVariant, IdlField, IdlType, IdlTypeDefinition,
+    IdlTypeDefinitionTy,
+};
 use heck::ToSnakeCase;
 use proc_macro2::{Ident, TokenStream};
 use quote::{format_ident, quote};
@@ -26,6 +29,27 @@ pub fn get_field_list_properties(
 pub fn get_type_list_properties(
     defs: &[IdlTypeDefinition],
     fields: &[IdlType],
+) -> FieldListProperties {
+    type_list_properties(defs, fields, &mut vec![])
+}
+
+pub fn get_variant_list_properties(
+    defs: &[IdlTypeDefinition],
+    variants: &[IdlEnumVariant],
+) -> FieldListProperties {
+    variant_list_properties(defs, variants, &mut vec![])
+}
+
+pub fn get_type_properties(defs: &[IdlTypeDefinition], ty: &IdlType) -> FieldListProperties {
+    type_properties(defs, ty, &mut vec![])
+}
+
+/// The property analysis functions track the typedefs being visited in `visiting`, so that
+/// recursive types are analyzed in finite time.
+fn type_list_properties(
+    defs: &[IdlTypeDefinition],
+    fields: &[IdlType],
+    visiting: &mut Vec<String>,
 ) -> FieldListProperties {
     fields.iter().fold(
         FieldListProperties {
@@ -33,7 +57,7 @@ pub fn get_type_list_properties(
             can_derive_default: true,
         },
         |acc, el| {
-            let inner_props = get_type_properties(defs, el);
+            let inner_props = type_properties(defs, el, visiting);
             let can_copy = acc.can_copy && inner_props.can_copy;
             let can_derive_default = acc.can_derive_default && inner_props.can_derive_default;
             FieldListProperties {
@@ -44,9 +68,10 @@ pub fn get_type_list_properties(
     )
 }
 
-pub fn get_variant_list_properties(
+fn variant_list_properties(
     defs: &[IdlTypeDefinition],
     variants: &[IdlEnumVariant],
+    visiting: &mut Vec<String>,
 ) -> FieldListProperties {
     variants.iter().fold(
         FieldListProperties {
@@ -55,8 +80,11 @@ pub fn get_variant_list_properties(
         },
         |acc, el| {
             let props = match &el.fields {
-                Some(EnumFields::Named(fields)) => get_field_list_properties(defs, fields),
-                Some(EnumFields::Tuple(fields)) => get_type_list_properties(defs, fields),
+                Some(EnumFields::Named(fields)) => {
+                    let types = fields.iter().map(|f| f.ty.clone()).collect::<Vec<_>>();
+                    type_list_properties(defs, &types, visiting)
+                }
+                Some(EnumFields::Tuple(fields)) => type_list_properties(defs, fields, visiting),
                 None => acc,
             };
             FieldListProperties {
@@ -67,7 +95,11 @@ pub fn get_variant_list_properties(
     )
 }
 
-pub fn get_type_properties(defs: &[IdlTypeDefinition], ty: &IdlType) -> FieldListProperties {
+fn type_properties(
+    defs: &[IdlTypeDefinition],
+    ty: &IdlType,
+    visiting: &mut Vec<String>,
+) -> FieldListProperties {
     match ty {
         IdlType::Bool
         | IdlType::U8
@@ -82,6 +114,8 @@ pub fn get_type_properties(defs: &[IdlTypeDefinition], ty: &IdlType) -> FieldLis
         | IdlType::F64
         | IdlType::U128
         | IdlType::I128
+        | IdlType::U256
+        | IdlType::I256
         | IdlType::PublicKey => FieldListProperties {
             can_copy: true,
             can_derive_default: true,
@@ -94,38 +128,533 @@ pub fn get_type_properties(defs: &[IdlTypeDefinition], ty: &IdlType) -> FieldLis
             can_copy: false,
             can_derive_default: true,
         },
-        IdlType::Defined(inner) => {
-            let def = defs.iter().find(|def| def.name == *inner).unwrap();
-            match &def.ty {
-                anchor_syn::idl::types::IdlTypeDefinitionTy::Struct { fields } => {
-                    get_field_list_properties(defs, fields)
-                }
-                anchor_syn::idl::types::IdlTypeDefinitionTy::Enum { variants } => {
-                    get_variant_list_properties(defs, variants)
-                }
-                _ => todo!(),
-            }
-        }
-        IdlType::Option(inner) => get_type_properties(defs, inner),
+        IdlType::Defined(name) => def_properties(defs, name, &[], visiting),
+        IdlType::DefinedWithTypeArgs { name, args } => def_properties(defs, name, args, visiting),
+        IdlType::Option(inner) => type_properties(defs, inner, visiting),
         IdlType::Array(inner, len) => {
-            let inner = get_type_properties(defs, inner);
+            let inner = type_properties(defs, inner, visiting);
             let can_derive_array_len = *len <= 32;
             FieldListProperties {
                 can_copy: inner.can_copy,
                 can_derive_default: can_derive_array_len && inner.can_derive_default,
             }
         }
-        _ => todo!(),
+        // Derives on generic typedefs are bounded by the same traits on their parameters.
+        IdlType::Generic(_) => FieldListProperties {
+            can_copy: true,
+            can_derive_default: true,
+        },
+        IdlType::GenericLenArray(inner, _) => FieldListProperties {
+            can_copy: type_properties(defs, inner, visiting).can_copy,
+            can_derive_default: false,
+        },
+    }
+}
+
+fn def_properties(
+    defs: &[IdlTypeDefinition],
+    name: &str,
+    args: &[IdlDefinedTypeArg],
+    visiting: &mut Vec<String>,
+) -> FieldListProperties {
+    // Typedefs are only reached again outside of a `Vec` if they are boxed, and a `Default`
+    // implementation could recurse endlessly.
+    if visiting.iter().any(|visited| visited == name) {
+        return FieldListProperties {
+            can_copy: false,
+            can_derive_default: false,
+        };
+    }
+    // External types are opaque.
+    let Some(def) = defs.iter().find(|def| def.name == *name) else {
+        return FieldListProperties::default();
+    };
+    visiting.push(name.to_string());
+    let mut props = def_ty_properties(defs, &def.ty, visiting);
+    if !args.is_empty() {
+        // The typedef only derives what its fields allow for any arguments, while the
+        // derives only apply if the arguments allow them.
+        let generics = def.generics.as_deref().unwrap_or_default();
+        let def_ty = map_def_ty_types(&def.ty, |ty| apply_type_args(ty, generics, args));
+        let applied = def_ty_properties(defs, &def_ty, visiting);
+        props = FieldListProperties {
+            can_copy: props.can_copy && applied.can_copy,
+            can_derive_default: props.can_derive_default && applied.can_derive_default,
+        };
+    }
+    visiting.pop();
+    props
+}
+
+fn def_ty_properties(
+    defs: &[IdlTypeDefinition],
+    def_ty: &IdlTypeDefinitionTy,
+    visiting: &mut Vec<String>,
+) -> FieldListProperties {
+    match def_ty {
+        IdlTypeDefinitionTy::Struct { fields } => {
+            let types = fields.iter().map(|f| f.ty.clone()).collect::<Vec<_>>();
+            type_list_properties(defs, &types, visiting)
+        }
+        IdlTypeDefinitionTy::Enum { variants } => variant_list_properties(defs, variants, visiting),
+        IdlTypeDefinitionTy::Alias { value } => type_properties(defs, value, visiting),
+    }
+}
+
+/// Maps the types of the fields of a typedef.
+fn map_def_ty_types(
+    def_ty: &IdlTypeDefinitionTy,
+    f: impl Fn(&IdlType) -> IdlType,
+) -> IdlTypeDefinitionTy {
+    let map_fields = |fields: &[IdlField]| {
+        fields
+            .iter()
+            .map(|field| IdlField {
+                ty: f(&field.ty),
+                ..field.clone()
+            })
+            .collect()
+    };
+    match def_ty {
+        IdlTypeDefinitionTy::Struct { fields } => IdlTypeDefinitionTy::Struct {
+            fields: map_fields(fields),
+        },
+        IdlTypeDefinitionTy::Enum { variants } => IdlTypeDefinitionTy::Enum {
+            variants: variants
+                .iter()
+                .map(|variant| IdlEnumVariant {
+                    name: variant.name.clone(),
+                    fields: variant.fields.as_ref().map(|fields| match fields {
+                        EnumFields::Named(fields) => EnumFields::Named(map_fields(fields)),
+                        EnumFields::Tuple(types) => {
+                            EnumFields::Tuple(types.iter().map(&f).collect())
+                        }
+                    }),
+                })
+                .collect(),
+        },
+        IdlTypeDefinitionTy::Alias { value } => IdlTypeDefinitionTy::Alias { value: f(value) },
+    }
+}
+
+/// Lists the types of the fields of a typedef.
+fn get_def_ty_types(def_ty: &IdlTypeDefinitionTy) -> Vec<&IdlType> {
+    match def_ty {
+        IdlTypeDefinitionTy::Struct { fields } => fields.iter().map(|field| &field.ty).collect(),
+        IdlTypeDefinitionTy::Enum { variants } => variants
+            .iter()
+            .flat_map(|variant| match &variant.fields {
+                Some(EnumFields::Named(fields)) => fields.iter().map(|field| &field.ty).collect(),
+                Some(EnumFields::Tuple(types)) => types.iter().collect(),
+                None => vec![],
+            })
+            .collect(),
+        IdlTypeDefinitionTy::Alias { value } => vec![value],
+    }
+}
+
+/// Returns the name of the generic parameter passed as a type argument, if any.
+fn type_arg_generic(arg: &IdlDefinedTypeArg) -> Option<&str> {
+    match arg {
+        IdlDefinedTypeArg::Generic(name) | IdlDefinedTypeArg::Type(IdlType::Generic(name)) => {
+            Some(name)
+        }
+        _ => None,
+    }
+}
+
+/// Applies the type arguments of a use site to a type of a generic typedef.
+pub fn apply_type_args(ty: &IdlType, generics: &[String], args: &[IdlDefinedTypeArg]) -> IdlType {
+    let lookup = |name: &str| {
+        generics
+            .iter()
+            .position(|generic| generic == name)
+            .and_then(|index| args.get(index))
+    };
+    let apply = |ty: &IdlType| Box::new(apply_type_args(ty, generics, args));
+    match ty {
+        IdlType::Generic(name) => match lookup(name) {
+            Some(IdlDefinedTypeArg::Type(ty)) => ty.clone(),
+            Some(IdlDefinedTypeArg::Generic(name)) => IdlType::Generic(name.clone()),
+            _ => ty.clone(),
+        },
+        IdlType::GenericLenArray(inner, len) => match lookup(len) {
+            Some(IdlDefinedTypeArg::Value(value)) => match value.parse() {
+                Ok(len) => IdlType::Array(apply(inner), len),
+                Err(_) => IdlType::GenericLenArray(apply(inner), value.clone()),
+            },
+            Some(arg) => match type_arg_generic(arg) {
+                Some(name) => IdlType::GenericLenArray(apply(inner), name.to_string()),
+                None => IdlType::GenericLenArray(apply(inner), len.clone()),
+            },
+            None => IdlType::GenericLenArray(apply(inner), len.clone()),
+        },
+        IdlType::Option(inner) => IdlType::Option(apply(inner)),
+        IdlType::Vec(inner) => IdlType::Vec(apply(inner)),
+        IdlType::Array(inner, len) => IdlType::Array(apply(inner), *len),
+        IdlType::DefinedWithTypeArgs {
+            name,
+            args: inner_args,
+        } => IdlType::DefinedWithTypeArgs {
+            name: name.clone(),
+            args: inner_args
+                .iter()
+                .map(|arg| match (type_arg_generic(arg).and_then(lookup), arg) {
+                    (Some(outer), _) => outer.clone(),
+                    (None, IdlDefinedTypeArg::Type(ty)) => {
+                        IdlDefinedTypeArg::Type(apply_type_args(ty, generics, args))
+                    }
+                    (None, arg) => arg.clone(),
+                })
+                .collect(),
+        },
+        _ => ty.clone(),
+    }
+}
+
+/// Replaces the typedefs of a type which are overridden by external types with the paths of
+/// those types, keyed by typedef name.
+pub fn override_type(ty: &IdlType, overrides: &BTreeMap<String, String>) -> IdlType {
+    let apply = |ty: &IdlType| Box::new(override_type(ty, overrides));
+    match ty {
+        IdlType::Defined(name) => IdlType::Defined(overrides.get(name).unwrap_or(name).clone()),
+        IdlType::DefinedWithTypeArgs { name, args } => IdlType::DefinedWithTypeArgs {
+            name: overrides.get(name).unwrap_or(name).clone(),
+            args: args
+                .iter()
+                .map(|arg| match arg {
+                    IdlDefinedTypeArg::Type(ty) => {
+                        IdlDefinedTypeArg::Type(override_type(ty, overrides))
+                    }
+                    arg => arg.clone(),
+                })
+                .collect(),
+        },
+        IdlType::Option(inner) => IdlType::Option(apply(inner)),
+        IdlType::Vec(inner) => IdlType::Vec(apply(inner)),
+        IdlType::Array(inner, len) => IdlType::Array(apply(inner), *len),
+        IdlType::GenericLenArray(inner, len) => IdlType::GenericLenArray(apply(inner), len.clone()),
+        _ => ty.clone(),
+    }
+}
+
+/// Overrides typedefs with external types: the typedefs are dropped and the types referring
+/// to them refer to the paths of the external types instead.
+///
+/// The external types are opaque to the generator, so the types holding them do not derive
+/// `Copy` or `Default`.
+pub fn apply_type_overrides(idl: &mut Idl, overrides: &BTreeMap<String, String>) {
+    if overrides.is_empty() {
+        return;
+    }
+    let apply = |ty: &IdlType| override_type(ty, overrides);
+    idl.types.retain(|def| !overrides.contains_key(&def.name));
+    for def in idl.types.iter_mut().chain(&mut idl.accounts) {
+        def.ty = map_def_ty_types(&def.ty, apply);
+    }
+    for ix in &mut idl.instructions {
+        for arg in &mut ix.args {
+            arg.ty = apply(&arg.ty);
+        }
+        if let Some(returns) = &mut ix.returns {
+            *returns = apply(returns);
+        }
+    }
+    for event in idl.events.iter_mut().flatten() {
+        for field in &mut event.fields {
+            field.ty = apply(&field.ty);
+        }
+    }
+    for constant in &mut idl.constants {
+        constant.ty = apply(&constant.ty);
+    }
+}
+
+/// A generic parameter of a typedef.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum GenericParam {
+    Type(String),
+    /// Const generics are only used as array lengths, hence always `usize`.
+    Const(String),
+}
+
+/// Adds the typedefs referred to by `ty` to `reached`, along with the typedefs they refer to.
+fn reach_typedefs(defs: &[IdlTypeDefinition], ty: &IdlType, reached: &mut BTreeSet<String>) {
+    match ty {
+        IdlType::Option(inner)
+        | IdlType::Vec(inner)
+        | IdlType::Array(inner, _)
+        | IdlType::GenericLenArray(inner, _) => reach_typedefs(defs, inner, reached),
+        IdlType::Defined(name) => reach_typedef(defs, name, reached),
+        IdlType::DefinedWithTypeArgs { name, args } => {
+            reach_typedef(defs, name, reached);
+            for arg in args {
+                if let IdlDefinedTypeArg::Type(ty) = arg {
+                    reach_typedefs(defs, ty, reached);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+fn reach_typedef(defs: &[IdlTypeDefinition], name: &str, reached: &mut BTreeSet<String>) {
+    if !reached.insert(name.to_string()) {
+        return;
+    }
+    if let Some(def) = defs.iter().find(|def| def.name == name) {
+        for ty in get_def_ty_types(&def.ty) {
+            reach_typedefs(defs, ty, reached);
+        }
+    }
+}
+
+/// Removes the typedefs which are not reachable from the instructions, accounts, events and
+/// constants of the IDL, nor from the typedefs of `keep`.
+pub fn prune_typedefs(idl: &mut Idl, keep: &BTreeSet<String>) {
+    let defs: Vec<IdlTypeDefinition> = idl.types.iter().chain(&idl.accounts).cloned().collect();
+    let mut reached = BTreeSet::new();
+    for name in idl.accounts.iter().map(|def| &def.name).chain(keep) {
+        reach_typedef(&defs, name, &mut reached);
+    }
+    for ix in &idl.instructions {
+        for arg in &ix.args {
+            reach_typedefs(&defs, &arg.ty, &mut reached);
+        }
+        if let Some(returns) = &ix.returns {
+            reach_typedefs(&defs, returns, &mut reached);
+        }
+    }
+    for field in idl.events.iter().flatten().flat_map(|event| &event.fields) {
+        reach_typedefs(&defs, &field.ty, &mut reached);
+    }
+    for constant in &idl.constants {
+        reach_typedefs(&defs, &constant.ty, &mut reached);
+    }
+    idl.types.retain(|def| reached.contains(&def.name));
+}
+
+/// Lists the generic parameters of a typedef.
+///
+/// IDLs do not tell type and const generics apart, so generics used as array lengths,
+/// directly or through other typedefs, are taken to be const generics.
+pub fn get_generic_params(
+    defs: &[IdlTypeDefinition],
+    def: &IdlTypeDefinition,
+) -> Vec<GenericParam> {
+    def.generics
+        .iter()
+        .flatten()
+        .map(|name| {
+            if is_const_generic(defs, def, name, &mut vec![]) {
+                GenericParam::Const(name.clone())
+            } else {
+                GenericParam::Type(name.clone())
+            }
+        })
+        .collect()
+}
+
+fn is_const_generic(
+    defs: &[IdlTypeDefinition],
+    def: &IdlTypeDefinition,
+    generic: &str,
+    visited: &mut Vec<(String, String)>,
+) -> bool {
+    let key = (def.name.clone(), generic.to_string());
+    if visited.contains(&key) {
+        return false;
+    }
+    visited.push(key);
+    get_def_ty_types(&def.ty)
+        .into_iter()
+        .any(|ty| uses_as_const_generic(defs, ty, generic, visited))
+}
+
+fn uses_as_const_generic(
+    defs: &[IdlTypeDefinition],
+    ty: &IdlType,
+    generic: &str,
+    visited: &mut Vec<(String, String)>,
+) -> bool {
+    match ty {
+        IdlType::GenericLenArray(inner, len) => {
+            len == generic || uses_as_const_generic(defs, inner, generic, visited)
+        }
+        IdlType::Option(inner) | IdlType::Vec(inner) | IdlType::Array(inner, _) => {
+            uses_as_const_generic(defs, inner, generic, visited)
+        }
+        IdlType::DefinedWithTypeArgs { name, args } => {
+            let Some(def) = defs.iter().find(|def| def.name == *name) else {
+                return false;
+            };
+            args.iter().enumerate().any(|(index, arg)| {
+                if type_arg_generic(arg) == Some(generic) {
+                    let param = def
+                        .generics
+                        .as_ref()
+                        .and_then(|generics| generics.get(index));
+                    param.is_some_and(|param| is_const_generic(defs, def, param, visited))
+                } else if let IdlDefinedTypeArg::Type(ty) = arg {
+                    uses_as_const_generic(defs, ty, generic, visited)
+                } else {
+                    false
+                }
+            })
+        }
+        _ => false,
+    }
+}
+
+/// Checks whether `ty` refers to the generic parameter `generic`.
+fn uses_generic(ty: &IdlType, generic: &str) -> bool {
+    match ty {
+        IdlType::Generic(name) => name == generic,
+        IdlType::GenericLenArray(inner, len) => len == generic || uses_generic(inner, generic),
+        IdlType::Option(inner) | IdlType::Vec(inner) | IdlType::Array(inner, _) => {
+            uses_generic(inner, generic)
+        }
+        IdlType::DefinedWithTypeArgs { args, .. } => args.iter().any(|arg| match arg {
+            IdlDefinedTypeArg::Type(ty) => uses_generic(ty, generic),
+            arg => type_arg_generic(arg) == Some(generic),
+        }),
+        _ => false,
+    }
+}
+
+/// Generates the generic parameters of a typedef, each bounded by `bound` if provided.
+pub fn generate_generic_params(params: &[GenericParam], bound: Option<TokenStream>) -> TokenStream {
+    if params.is_empty() {
+        return quote! {};
+    }
+    let params = params.iter().map(|param| match param {
+        GenericParam::Type(name) => {
+            let name = format_ident!("{}", name);
+            match &bound {
+                Some(bound) => quote! { #name: #bound },
+                None => quote! { #name },
+            }
+        }
+        GenericParam::Const(name) => {
+            let name = format_ident!("{}", name);
+            quote! { const #name: usize }
+        }
+    });
+    quote! { <#(#params),*> }
+}
+
+/// Generates the generic arguments referring to the parameters of a typedef.
+pub fn generate_generic_args(params: &[GenericParam]) -> TokenStream {
+    if params.is_empty() {
+        return quote! {};
+    }
+    let args = params.iter().map(|param| match param {
+        GenericParam::Type(name) | GenericParam::Const(name) => format_ident!("{}", name),
+    });
+    quote! { <#(#args),*> }
+}
+
+/// Checks whether `ty` holds the typedef `target`.
+///
+/// With `inline_only`, typedefs behind a heap allocation like that of a `Vec` are not
+/// considered held.
+fn holds(
+    defs: &[IdlTypeDefinition],
+    ty: &IdlType,
+    target: &str,
+    inline_only: bool,
+    visited: &mut Vec<String>,
+) -> bool {
+    match ty {
+        IdlType::Option(inner) | IdlType::Array(inner, _) | IdlType::GenericLenArray(inner, _) => {
+            holds(defs, inner, target, inline_only, visited)
+        }
+        IdlType::Vec(inner) if !inline_only => holds(defs, inner, target, inline_only, visited),
+        IdlType::Defined(name) => def_holds(defs, name, target, inline_only, visited),
+        IdlType::DefinedWithTypeArgs { name, args } => {
+            def_holds(defs, name, target, inline_only, visited)
+                || args.iter().any(|arg| match arg {
+                    IdlDefinedTypeArg::Type(ty) => holds(defs, ty, target, inline_only, visited),
+                    _ => false,
+                })
+        }
+        _ => false,
+    }
+}
+
+fn def_holds(
+    defs: &[IdlTypeDefinition],
+    name: &str,
+    target: &str,
+    inline_only: bool,
+    visited: &mut Vec<String>,
+) -> bool {
+    if name == target {
+        return true;
+    }
+    if visited.iter().any(|visited| visited == name) {
+        return false;
+    }
+    visited.push(name.to_string());
+    let Some(def) = defs.iter().find(|def| def.name == name) else {
+        return false;
+    };
+    get_def_ty_types(&def.ty)
+        .into_iter()
+        .any(|ty| holds(defs, ty, target, inline_only, visited))
+}
+
+/// Checks whether the typedef `name` refers to itself, directly or through other typedefs.
+pub fn is_recursive(defs: &[IdlTypeDefinition], name: &str) -> bool {
+    defs.iter()
+        .find(|def| def.name == name)
+        .map(|def| {
+            get_def_ty_types(&def.ty)
+                .into_iter()
+                .any(|ty| holds(defs, ty, name, false, &mut vec![]))
+        })
+        .unwrap_or_default()
+}
+
+/// Converts the type of a field of the typedef `parent` to a [String] of the Rust
+/// representation.
+///
+/// Typedefs holding `parent` inline are boxed, as `parent` would be infinitely sized otherwise.
+pub fn field_ty_to_rust_type(defs: &[IdlTypeDefinition], parent: &str, ty: &IdlType) -> String {
+    match ty {
+        IdlType::Defined(_) | IdlType::DefinedWithTypeArgs { .. }
+            if holds(defs, ty, parent, true, &mut vec![]) =>
+        {
+            format!("Box<{}>", crate::ty_to_rust_type(ty))
+        }
+        IdlType::Option(inner) => format!("Option<{}>", field_ty_to_rust_type(defs, parent, inner)),
+        IdlType::Array(inner, len) => {
+            format!("[{}; {}]", field_ty_to_rust_type(defs, parent, inner), len)
+        }
+        IdlType::GenericLenArray(inner, len) => {
+            format!("[{}; {}]", field_ty_to_rust_type(defs, parent, inner), len)
+        }
+        _ => crate::ty_to_rust_type(ty),
     }
 }
 
 /// Generates struct fields from a list of [IdlField]s.
 pub fn generate_fields(fields: &[IdlField]) -> TokenStream {
+    generate_fields_with_types(fields, crate::ty_to_rust_type)
+}
+
+/// Generates struct fields, converting their types with `ty_to_rust_type`.
+fn generate_fields_with_types(
+    fields: &[IdlField],
+    ty_to_rust_type: impl Fn(&IdlType) -> String,
+) -> TokenStream {
     let fields_rendered = fields.iter().map(|arg| {
-        let name = format_ident!("{}", arg.name.to_snake_case());
-        let type_name = crate::ty_to_rust_type(&arg.ty);
+        let name = crate::generate_ident(&arg.name.to_snake_case());
+        let type_name = ty_to_rust_type(&arg.ty);
         let stream: proc_macro2::TokenStream = type_name.parse().unwrap();
+        let docs = crate::generate_docs(arg.docs.as_deref());
         quote! {
+            #docs
             pub #name: #stream
         }
     });
@@ -134,15 +663,245 @@ pub fn generate_fields(fields: &[IdlField]) -> TokenStream {
     }
 }
 
+/// Checks whether the fields are those of a tuple struct, i.e. named by their position.
+pub fn is_tuple_struct(fields: &[IdlField]) -> bool {
+    !fields.is_empty()
+        && fields
+            .iter()
+            .enumerate()
+            .all(|(index, field)| field.name == index.to_string())
+}
+
+/// Generates the body of a struct, either `{ ... }` or `( ... );` for tuple structs.
+pub fn generate_struct_body(
+    defs: &[IdlTypeDefinition],
+    struct_name: &str,
+    fields: &[IdlField],
+) -> TokenStream {
+    let ty_to_rust_type = |ty: &IdlType| field_ty_to_rust_type(defs, struct_name, ty);
+    if is_tuple_struct(fields) {
+        let fields_rendered = fields.iter().map(|field| {
+            let stream: TokenStream = ty_to_rust_type(&field.ty).parse().unwrap();
+            let docs = crate::generate_docs(field.docs.as_deref());
+            quote! {
+                #docs
+                pub #stream
+            }
+        });
+        quote! {
+            ( #(#fields_rendered),* );
+        }
+    } else {
+        let fields_rendered = generate_fields_with_types(fields, ty_to_rust_type);
+        quote! {
+            { #fields_rendered }
+        }
+    }
+}
+
+/// Generates the Borsh implementations of a recursive typedef.
+///
+/// The derive macros bound the implementations on those of the field types, which the
+/// compiler cannot resolve for a type containing itself.
+///
+/// `deserialize` reads `reader` with the method returned by [borsh_read_method].
+fn generate_borsh_impls(
+    name: &Ident,
+    generics: &[GenericParam],
+    serialize: TokenStream,
+    deserialize: TokenStream,
+) -> TokenStream {
+    let ser_generics = generate_generic_params(generics, Some(quote! { AnchorSerialize }));
+    let de_generics = generate_generic_params(generics, Some(quote! { AnchorDeserialize }));
+    let ty_generics = generate_generic_args(generics);
+    // borsh 0.10 requires `deserialize_reader` and provides `deserialize`, while borsh 0.9
+    // only has `deserialize`.
+    let deserialize_fn = if cfg!(feature = "compat-borsh-0-10") {
+        quote! {
+            fn deserialize_reader<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self> {
+                #deserialize
+            }
+        }
+    } else {
+        quote! {
+            fn deserialize(reader: &mut &[u8]) -> std::io::Result<Self> {
+                #deserialize
+            }
+        }
+    };
+    quote! {
+        impl #ser_generics AnchorSerialize for #name #ty_generics {
+            fn serialize<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+                #serialize
+                Ok(())
+            }
+        }
+
+        impl #de_generics AnchorDeserialize for #name #ty_generics {
+            #deserialize_fn
+        }
+    }
+}
+
+/// Name of the `AnchorDeserialize` method reading a value from the `reader` of the
+/// implementations generated by [generate_borsh_impls].
+fn borsh_read_method() -> Ident {
+    if cfg!(feature = "compat-borsh-0-10") {
+        format_ident!("deserialize_reader")
+    } else {
+        format_ident!("deserialize")
+    }
+}
+
+/// Generates the Borsh implementations of a recursive struct.
+fn generate_struct_borsh_impls(
+    struct_name: &Ident,
+    generics: &[GenericParam],
+    fields: &[IdlField],
+) -> TokenStream {
+    let read = borsh_read_method();
+    let (serialize, deserialize) = if is_tuple_struct(fields) {
+        let indices = (0..fields.len()).map(syn::Index::from);
+        let values = fields
+            .iter()
+            .map(|_| quote! { AnchorDeserialize::#read(reader)? });
+        (
+            quote! { #(AnchorSerialize::serialize(&self.#indices, writer)?;)* },
+            quote! { Ok(Self(#(#values),*)) },
+        )
+    } else {
+        let names: Vec<_> = fields
+            .iter()
+            .map(|field| crate::generate_ident(&field.name.to_snake_case()))
+            .collect();
+        (
+            quote! { #(AnchorSerialize::serialize(&self.#names, writer)?;)* },
+            quote! { Ok(Self { #(#names: AnchorDeserialize::#read(reader)?),* }) },
+        )
+    };
+    generate_borsh_impls(struct_name, generics, serialize, deserialize)
+}
+
+/// Generates the Borsh implementations of a recursive enum.
+///
+/// Variants are prefixed by their `u8` index, as with the derive macros.
+fn generate_enum_borsh_impls(
+    enum_name: &Ident,
+    generics: &[GenericParam],
+    variants: &[IdlEnumVariant],
+) -> TokenStream {
+    let read = borsh_read_method();
+    let (ser_arms, de_arms): (Vec<_>, Vec<_>) = variants
+        .iter()
+        .enumerate()
+        .map(|(index, variant)| {
+            let index = index as u8;
+            let variant_name = format_ident!("{}", variant.name);
+            let names: Vec<_> = match &variant.fields {
+                Some(EnumFields::Named(fields)) => fields
+                    .iter()
+                    .map(|field| crate::generate_ident(&field.name.to_snake_case()))
+                    .collect(),
+                Some(EnumFields::Tuple(types)) => (0..types.len())
+                    .map(|index| format_ident!("field_{}", index))
+                    .collect(),
+                None => vec![],
+            };
+            let (pattern, value) = match &variant.fields {
+                Some(EnumFields::Named(_)) => (
+                    quote! { { #(#names),* } },
+                    quote! { { #(#names: AnchorDeserialize::#read(reader)?),* } },
+                ),
+                Some(EnumFields::Tuple(_)) => {
+                    let values = names
+                        .iter()
+                        .map(|_| quote! { AnchorDeserialize::#read(reader)? });
+                    (quote! { (#(#names),*) }, quote! { (#(#values),*) })
+                }
+                None => (quote! {}, quote! {}),
+            };
+            (
+                quote! {
+                    Self::#variant_name #pattern => {
+                        AnchorSerialize::serialize(&#index, writer)?;
+                        #(AnchorSerialize::serialize(#names, writer)?;)*
+                    }
+                },
+                quote! {
+                    #index => Ok(Self::#variant_name #value)
+                },
+            )
+        })
+        .unzip();
+    let serialize = quote! {
+        match self {
+            #(#ser_arms)*
+        }
+    };
+    let deserialize = quote! {
+        let index: u8 = AnchorDeserialize::#read(reader)?;
+        match index {
+            #(#de_arms,)*
+            _ => Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("unexpected variant index: {}", index),
+            )),
+        }
+    };
+    generate_borsh_impls(enum_name, generics, serialize, deserialize)
+}
+
+/// Generates a type alias.
+pub fn generate_alias(
+    alias_name: &Ident,
+    generics: &[GenericParam],
+    value: &IdlType,
+) -> TokenStream {
+    let generic_params = generate_generic_params(generics, None);
+    let stream: TokenStream = crate::ty_to_rust_type(value).parse().unwrap();
+    quote! {
+        pub type #alias_name #generic_params = #stream;
+    }
+}
+
+/// Generates the `repr` attribute of a zero copy struct.
+///
+/// Packed structs are `repr(C, packed)`, since rustc reorders the fields of `repr(packed)`
+/// structs and the IDL gives their offsets in declaration order.
+pub fn generate_zero_copy_repr(opts: StructOpts) -> TokenStream {
+    if opts.packed {
+        quote! {
+            #[repr(C, packed)]
+        }
+    } else if let Some(align) = opts.align {
+        let align = proc_macro2::Literal::usize_unsuffixed(align);
+        quote! {
+            #[repr(C, align(#align))]
+        }
+    } else {
+        quote! {
+            #[repr(C)]
+        }
+    }
+}
+
 /// Generates a struct.
 pub fn generate_struct(
     defs: &[IdlTypeDefinition],
     struct_name: &Ident,
+    generics: &[GenericParam],
     fields: &[IdlField],
     opts: StructOpts,
+    attrs: &TokenStream,
 ) -> TokenStream {
-    let fields_rendered = generate_fields(fields);
+    let body = generate_struct_body(
+        defs,
+        &struct_name.to_string(),
+        &crate::get_generated_fields(fields, opts),
+    );
+    let recursive = !opts.zero_copy && is_recursive(defs, &struct_name.to_string());
     let props = get_field_list_properties(defs, fields);
+    let generic_params = generate_generic_params(generics, None);
 
     let derive_default = if props.can_derive_default {
         quote! {
@@ -152,15 +911,7 @@ pub fn generate_struct(
         quote! {}
     };
     let derive_serializers = if opts.zero_copy {
-        let repr = if opts.packed {
-            quote! {
-                #[repr(packed)]
-            }
-        } else {
-            quote! {
-                #[repr(C)]
-            }
-        };
+        let repr = generate_zero_copy_repr(opts);
         quote! {
             #[zero_copy]
             #repr
@@ -173,19 +924,91 @@ pub fn generate_struct(
         } else {
             quote! {}
         };
-        quote! {
-            #[derive(AnchorSerialize, AnchorDeserialize, Clone)]
-            #derive_copy
+        if recursive {
+            quote! {
+                #[derive(Clone)]
+                #derive_copy
+            }
+        } else {
+            quote! {
+                #[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+                #derive_copy
+            }
         }
     };
+    let borsh_impls = if recursive {
+        generate_struct_borsh_impls(struct_name, generics, fields)
+    } else {
+        quote! {}
+    };
 
     quote! {
         #derive_serializers
         #[derive(Debug)]
         #derive_default
-        pub struct #struct_name {
-            #fields_rendered
+        #attrs
+        pub struct #struct_name #generic_params #body
+
+        #borsh_impls
+    }
+}
+
+/// Generates the payload of a variant of the enum `enum_name`.
+pub fn generate_variant_fields(
+    defs: &[IdlTypeDefinition],
+    enum_name: &str,
+    fields: Option<&EnumFields>,
+) -> TokenStream {
+    let ty_to_rust_type = |ty: &IdlType| field_ty_to_rust_type(defs, enum_name, ty);
+    match fields {
+        Some(EnumFields::Named(fields)) => {
+            let fields_rendered = fields.iter().map(|field| {
+                let name = crate::generate_ident(&field.name.to_snake_case());
+                let stream: TokenStream = ty_to_rust_type(&field.ty).parse().unwrap();
+                let docs = crate::generate_docs(field.docs.as_deref());
+                quote! {
+                    #docs
+                    #name: #stream
+                }
+            });
+            quote! {
+                { #(#fields_rendered),* }
+            }
         }
+        Some(EnumFields::Tuple(types)) => {
+            let types_rendered = types.iter().map(|ty| {
+                let stream: TokenStream = ty_to_rust_type(ty).parse().unwrap();
+                stream
+            });
+            quote! {
+                ( #(#types_rendered),* )
+            }
+        }
+        None => quote! {},
+    }
+}
+
+/// Generates the default value of the payload of an enum variant.
+fn generate_variant_default(fields: Option<&EnumFields>) -> TokenStream {
+    match fields {
+        Some(EnumFields::Named(fields)) => {
+            let fields_rendered = fields.iter().map(|field| {
+                let name = crate::generate_ident(&field.name.to_snake_case());
+                quote! {
+                    #name: Default::default()
+                }
+            });
+            quote! {
+                { #(#fields_rendered),* }
+            }
+        }
+        Some(EnumFields::Tuple(types)) => {
+            let types_rendered = types.iter().map(|_| quote! { Default::default() });
+            quote! {
+                ( #(#types_rendered),* )
+            }
+        }
+        None => quote! {},
     }
 }
 
@@ -193,10 +1016,18 @@ pub fn generate_struct(
 pub fn generate_enum(
     defs: &[IdlTypeDefinition],
     enum_name: &Ident,
+    generics: &[GenericParam],
     variants: &[IdlEnumVariant],
 ) -> TokenStream {
-    let variant_idents = variants.iter().map(|v| format_ident!("{}", v.name));
+    let variants_rendered = variants.iter().map(|v| {
+        let variant_name = format_ident!("{}", v.name);
+        let fields = generate_variant_fields(defs, &enum_name.to_string(), v.fields.as_ref());
+        quote! {
+            #variant_name #fields
+        }
+    });
     let props = get_variant_list_properties(defs, variants);
+    let generic_params = generate_generic_params(generics, None);
 
     let derive_copy = if props.can_copy {
         quote! {
@@ -206,42 +1037,272 @@ pub fn generate_enum(
         quote! {}
     };
 
-    let default_variant = format_ident!("{}", variants.first().unwrap().name);
+    // Only the payload of the first variant needs to implement `Default`.
+    let first_variant = variants.first().filter(|variant| {
+        get_variant_list_properties(defs, std::slice::from_ref(*variant)).can_derive_default
+    });
+    let impl_default = if let Some(first_variant) = first_variant {
+        let default_variant = format_ident!("{}", first_variant.name);
+        let default_fields = generate_variant_default(first_variant.fields.as_ref());
+        // Only the type parameters of the default payload need to implement `Default`.
+        let payload_types: Vec<&IdlType> = match &first_variant.fields {
+            Some(EnumFields::Named(fields)) => fields.iter().map(|field| &field.ty).collect(),
+            Some(EnumFields::Tuple(types)) => types.iter().collect(),
+            None => vec![],
+        };
+        let impl_generics = generics.iter().map(|param| match param {
+            GenericParam::Type(name) => {
+                let ident = format_ident!("{}", name);
+                if payload_types.iter().any(|ty| uses_generic(ty, name)) {
+                    quote! { #ident: Default }
+                } else {
+                    quote! { #ident }
+                }
+            }
+            GenericParam::Const(name) => {
+                let ident = format_ident!("{}", name);
+                quote! { const #ident: usize }
+            }
+        });
+        let impl_generics = if generics.is_empty() {
+            quote! {}
+        } else {
+            quote! { <#(#impl_generics),*> }
+        };
+        let ty_generics = generate_generic_args(generics);
+        quote! {
+            impl #impl_generics Default for #enum_name #ty_generics {
+                fn default() -> Self {
+                    Self::#default_variant #default_fields
+                }
+            }
+        }
+    } else {
+        quote! {}
+    };
+
+    let (derive_borsh, borsh_impls) = if is_recursive(defs, &enum_name.to_string()) {
+        (
+            quote! {},
+            generate_enum_borsh_impls(enum_name, generics, variants),
+        )
+    } else {
+        (
+            quote! {
+                #[derive(AnchorSerialize, AnchorDeserialize)]
+            },
+            quote! {},
+        )
+    };
 
     quote! {
-        #[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+        #derive_borsh
+        #[derive(Clone, Debug)]
         #derive_copy
-        pub enum #enum_name {
-            #(#variant_idents),*
+        pub enum #enum_name #generic_params {
+            #(#variants_rendered),*
         }
 
-        impl Default for #enum_name {
-            fn default() -> Self {
-                Self::#default_variant
+        #impl_default
+        #borsh_impls
+    }
+}
+
+/// Lists the traits which the generated code derives or implements for a typedef or an
+/// account, whether it is generated as a struct or an enum.
+///
+/// Borsh traits are listed under both their Anchor and Borsh names.
+pub fn get_generated_traits(
+    defs: &[IdlTypeDefinition],
+    def: &IdlTypeDefinition,
+    opts: StructOpts,
+) -> Vec<&'static str> {
+    const BORSH_TRAITS: [&str; 4] = [
+        "AnchorSerialize",
+        "AnchorDeserialize",
+        "BorshSerialize",
+        "BorshDeserialize",
+    ];
+    let mut traits = vec!["Clone", "Debug"];
+    match &def.ty {
+        IdlTypeDefinitionTy::Struct { fields } => {
+            let props = get_field_list_properties(defs, fields);
+            if opts.zero_copy {
+                traits.extend(["Copy", "Pod", "Zeroable"]);
+            } else {
+                traits.extend(BORSH_TRAITS);
+                if props.can_copy {
+                    traits.push("Copy");
+                }
+            }
+            if props.can_derive_default {
+                traits.push("Default");
+            }
+        }
+        IdlTypeDefinitionTy::Enum { variants } => {
+            traits.extend(BORSH_TRAITS);
+            if get_variant_list_properties(defs, variants).can_copy {
+                traits.push("Copy");
+            }
+            if variants.first().is_some_and(|variant| {
+                get_variant_list_properties(defs, std::slice::from_ref(variant)).can_derive_default
+            }) {
+                traits.push("Default");
             }
         }
+        IdlTypeDefinitionTy::Alias { .. } => return vec![],
     }
+    traits
 }
 
-/// Generates structs and enums.
+/// Generates structs, enums and type aliases.
+///
+/// `type_attrs` are the extra attributes of typedefs, keyed by name.
 pub fn generate_typedefs(
     typedefs: &[IdlTypeDefinition],
     struct_opts: &BTreeMap<String, StructOpts>,
+    type_attrs: &BTreeMap<String, TokenStream>,
 ) -> TokenStream {
     let defined = typedefs.iter().map(|def| {
         let struct_name = format_ident!("{}", def.name);
-        match &def.ty {
-            anchor_syn::idl::types::IdlTypeDefinitionTy::Struct { fields } => {
+        let docs = crate::generate_docs(def.docs.as_deref());
+        let generics = get_generic_params(typedefs, def);
+        let attrs = type_attrs.get(&def.name).cloned().unwrap_or_default();
+        let item = match &def.ty {
+            IdlTypeDefinitionTy::Struct { fields } => {
                 let opts = struct_opts.get(&def.name).copied().unwrap_or_default();
-                generate_struct(typedefs, &struct_name, fields, opts)
+                let item = generate_struct(typedefs, &struct_name, &generics, fields, opts, &attrs);
+                let assertions = crate::generate_layout_assertions(typedefs, struct_opts, def);
+                quote! {
+                    #item
+                    #assertions
+                }
             }
-            anchor_syn::idl::types::IdlTypeDefinitionTy::Enum { variants } => {
-                generate_enum(typedefs, &struct_name, variants)
+            IdlTypeDefinitionTy::Enum { variants } => {
+                let item = generate_enum(typedefs, &struct_name, &generics, variants);
+                quote! {
+                    #attrs
+                    #item
+                }
             }
-            _ => todo!(),
+            IdlTypeDefinitionTy::Alias { value } => {
+                let item = generate_alias(&struct_name, &generics, value);
+                quote! {
+                    #attrs
+                    #item
+                }
+            }
+        };
+        quote! {
+            #docs
+            #item
         }
     });
     quote! {
         #(#defined)*
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn type_names(idl: &Idl) -> Vec<&str> {
+        idl.types.iter().map(|def| def.name.as_str()).collect()
+    }
+
+    fn struct_type(name: &str, field_type: &str) -> String {
+        format!(
+            r#"{{
+                "name": "{}",
+                "type": {{
+                    "kind": "struct",
+                    "fields": [{{ "name": "value", "type": {} }}]
+                }}
+            }}"#,
+            name, field_type
+        )
+    }
+
+    fn idl() -> Idl {
+        let types = [
+            struct_type("Arg", r#"{ "vec": { "defined": "Nested" } }"#),
+            struct_type("Nested", r#"{ "option": { "defined": "Arg" } }"#),
+            struct_type("AccountField", r#""u8""#),
+            struct_type("EventField", r#""u8""#),
+            struct_type("ConstType", r#""u8""#),
+            struct_type("Returned", r#""u8""#),
+            struct_type("Kept", r#"{ "array": [{ "defined": "KeptField" }, 2] }"#),
+            struct_type("KeptField", r#""u8""#),
+            struct_type("Unused", r#"{ "defined": "UnusedField" }"#),
+            struct_type("UnusedField", r#""u8""#),
+        ];
+        let json = format!(
+            r#"{{
+                "version": "0.1.0",
+                "name": "test",
+                "instructions": [
+                    {{
+                        "name": "swap",
+                        "accounts": [],
+                        "args": [{{ "name": "arg", "type": {{ "defined": "Arg" }} }}],
+                        "returns": {{ "defined": "Returned" }}
+                    }}
+                ],
+                "accounts": [{}],
+                "types": [{}],
+                "events": [
+                    {{
+                        "name": "Swapped",
+                        "fields": [
+                            {{ "name": "value", "type": {{ "defined": "EventField" }}, "index": false }}
+                        ]
+                    }}
+                ],
+                "constants": [
+                    {{ "name": "DEFAULT", "type": {{ "defined": "ConstType" }}, "value": "0" }}
+                ]
+            }}"#,
+            struct_type("Pool", r#"{ "defined": "AccountField" }"#),
+            types.join(",")
+        );
+        crate::parse_idl(&json).unwrap().0
+    }
+
+    #[test]
+    fn prune_unreachable_typedefs() {
+        let mut idl = idl();
+        prune_typedefs(&mut idl, &BTreeSet::new());
+        assert_eq!(
+            type_names(&idl),
+            [
+                "Arg",
+                "Nested",
+                "AccountField",
+                "EventField",
+                "ConstType",
+                "Returned"
+            ]
+        );
+        assert_eq!(idl.accounts.len(), 1);
+    }
+
+    #[test]
+    fn prune_keeps_listed_typedefs() {
+        let mut idl = idl();
+        prune_typedefs(&mut idl, &BTreeSet::from(["Kept".to_string()]));
+        assert!(type_names(&idl).ends_with(&["Kept", "KeptField"]));
+        assert!(!type_names(&idl).contains(&"Unused"));
+    }
+
+    #[test]
+    fn prune_follows_remaining_instructions() {
+        let mut idl = idl();
+        idl.instructions.clear();
+        prune_typedefs(&mut idl, &BTreeSet::new());
+        assert_eq!(
+            type_names(&idl),
+            ["AccountField", "EventField", "ConstType"]
+        );
+    }
+}
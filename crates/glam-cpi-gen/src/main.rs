@@ -1,4 +1,4 @@
-use anchor_idl::GeneratorOptions;
+use anchor_idl::{Generator, GeneratorError, GeneratorOptions, Severity};
 use clap::{Parser, Subcommand};
 use prettyplease::unparse;
 use proc_macro2::TokenStream;
@@ -87,9 +87,33 @@ enum Commands {
 fn prettify(tokens: TokenStream) -> String {
     // println!("tokens: {}", tokens);
     let syntax_tree: File = parse2(tokens).expect("Failed to parse TokenStream");
-    let pretty_code = unparse(&syntax_tree);
+    unparse(&syntax_tree)
+}
+
+fn exit_with_error(err: GeneratorError) -> ! {
+    eprintln!("error: {}", err);
+    std::process::exit(1);
+}
 
-    pretty_code
+fn to_generator(opts: &GeneratorOptions) -> Generator {
+    opts.try_to_generator()
+        .unwrap_or_else(|err| exit_with_error(err))
+}
+
+/// Reports the lint findings of the IDL, exiting if any of them is an error.
+///
+/// Only the `cpi` command lints the IDL, the GLAM commands generate code as they always did.
+fn lint(generator: &Generator) {
+    let findings = generator.lint();
+    findings
+        .iter()
+        .for_each(|finding| eprintln!("{}: {}", finding.severity, finding));
+    if findings
+        .iter()
+        .any(|finding| finding.severity == Severity::Error)
+    {
+        std::process::exit(1);
+    }
 }
 
 fn main() {
@@ -105,14 +129,19 @@ fn main() {
                 idl_path,
                 ..Default::default()
             };
-            let generator = opts.to_generator();
+            let generator = to_generator(&opts);
+            lint(&generator);
 
             let mut token_stream = TokenStream::new();
             token_stream.extend(quote! {
                 use anchor_lang::declare_id;
                 declare_id!(#program_id);
             });
-            token_stream.extend(generator.generate_cpi_interface());
+            token_stream.extend(
+                generator
+                    .generate_cpi_interface()
+                    .unwrap_or_else(|err| exit_with_error(err)),
+            );
             let pretty_code = prettify(token_stream);
 
             if let Some(output_file) = output {
@@ -135,7 +164,7 @@ fn main() {
                 glam_codegen_config: config,
                 ..Default::default()
             };
-            let generator = opts.to_generator();
+            let generator = to_generator(&opts);
 
             let (glam_code, _) = generator.generate_glam_code(
                 &ixs.unwrap_or_default(),
@@ -164,7 +193,7 @@ fn main() {
                 glam_codegen_config: config,
                 ..Default::default()
             };
-            let generator = opts.to_generator();
+            let generator = to_generator(&opts);
 
             let (_, mut ixs_remapping) = generator.generate_glam_code(
                 &ixs.unwrap_or_default(),
@@ -0,0 +1,31 @@
+//! Autogenerated CPI client for a program with an Anchor 0.30 IDL.
+//!
+//! This crate was automatically generated by
+//! [anchor-gen](https://github.com/saber-hq/anchor-gen), a crate for generating
+//! Anchor CPI helpers from JSON IDLs.
+
+anchor_gen::generate_cpi_interface!(
+    idl_path = "idl.json",
+    aligned_int128,
+    derive(PartialEq, Eq, Hash => [List, Versioned]),
+    attr("#[derive(PartialEq)] #[allow(dead_code)]" => [WideAccount, Expr]),
+    type_overrides(Price = "crate::shared::Price", Maybe = "crate::shared::Maybe"),
+);
+
+/// Types shared with other crates, used instead of the typedefs of the IDL.
+pub mod shared {
+    use anchor_lang::prelude::*;
+
+    #[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq)]
+    pub struct Price(pub u64);
+
+    #[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq)]
+    pub enum Maybe<T> {
+        Nothing,
+        Just(T),
+    }
+}
+
+pub use shared::{Maybe, Price};
+
+declare_id!("whirLbMiicVdio4qvUfM5KAg6Ct8VwpYzGff3uctyCc");
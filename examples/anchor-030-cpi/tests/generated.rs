@@ -0,0 +1,383 @@
+//! Tests of the code generated from the Anchor 0.30 IDL.
+
+use anchor_030_cpi::*;
+use anchor_lang::prelude::*;
+use anchor_lang::Discriminator;
+
+#[test]
+fn pdas() {
+    let authority = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let market = Pubkey::new_unique();
+    let args = client::args::InitializeUser {
+        params: InitParams {
+            sub_id: 3,
+            name: "bob".to_string(),
+        },
+        holder: holder(),
+    };
+    let accounts = client::accounts::InitializeUser {
+        authority,
+        market,
+        position: pda::initialize_user::find_position_address(&Pubkey::default()).0,
+        InitializeUser_group: client::accounts::InitializeUserGroup {
+            mint,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let ix = client::build_initialize_user(&accounts, &args, &[]);
+    let (user, _) =
+        Pubkey::find_program_address(&[b"user", authority.as_ref(), &3u16.to_le_bytes()], &ID);
+    assert_eq!(
+        pda::initialize_user::find_user_address(&authority, 3).0,
+        user
+    );
+    let keys: Vec<Pubkey> = ix.accounts.iter().map(|m| m.pubkey).collect();
+    assert_eq!(keys[0], user);
+    assert_eq!(
+        keys[1],
+        Pubkey::find_program_address(&[b"user_stats", authority.as_ref(), b"bob"], &ID).0
+    );
+    assert_eq!(keys[3], Pubkey::find_program_address(&[b"state"], &ID).0);
+    assert_eq!(
+        keys[4],
+        Pubkey::find_program_address(&[b"vault", user.as_ref()], &ID).0
+    );
+    assert_eq!(keys[5], mint);
+    let program = Pubkey::new_from_array(core::array::from_fn(|i| i as u8));
+    assert_eq!(
+        keys[8],
+        Pubkey::find_program_address(&[authority.as_ref(), mint.as_ref()], &program).0
+    );
+    assert_eq!(keys[9], Pubkey::default());
+    assert!(ix.accounts[0].is_writable && ix.accounts[2].is_signer);
+}
+
+fn holder() -> Holder {
+    Holder {
+        a: Wrapper {
+            inner: 1,
+            data: [2; 4],
+        },
+        b: Maybe::Just(ID),
+        c: Outer {
+            wrapped: Wrapper {
+                inner: 3,
+                data: [4; 2],
+            },
+        },
+    }
+}
+
+#[test]
+fn generics() {
+    let holder = holder();
+    let bytes = holder.try_to_vec().unwrap();
+    assert_eq!(bytes.len(), 8 + 32 + 1 + 32 + 1 + 2);
+    let decoded = Holder::try_from_slice(&bytes).unwrap();
+    assert_eq!(decoded.c.wrapped.data, [4; 2]);
+    let _copy: Maybe<u8> = Maybe::Nothing;
+}
+
+#[test]
+fn int256() {
+    let big = constants::BIG;
+    let (low, high) = big.to_halves();
+    assert_eq!(U256::from_halves(low, high), big);
+    assert!(high > 0);
+    assert!(u128::try_from(big).is_err());
+    assert_eq!(u128::try_from(U256::from(7u64)), Ok(7));
+    assert!(big > U256::from(u128::MAX));
+    assert_eq!(i128::try_from(constants::NEG), Ok(-5));
+    assert!(constants::NEG < I256::from(-4i8));
+    assert!(constants::NEG < I256::ZERO);
+    assert_eq!(i128::try_from(I256::from(i128::MIN)), Ok(i128::MIN));
+    assert!(i128::try_from(I256::from_halves(1 << 127, 0)).is_err());
+    assert!(i128::try_from(I256::from_halves(0, -1)).is_err());
+    assert!(I256::from_halves(0, -1) < I256::from(i128::MIN));
+    assert!(I256::from(i128::MIN) < I256::from(-1i8));
+    let wide = Wide {
+        u: big,
+        i: constants::NEG,
+    };
+    let bytes = wide.try_to_vec().unwrap();
+    assert_eq!(bytes.len(), 64);
+    assert_eq!(
+        &bytes[32..],
+        &[0xfb].into_iter().chain([0xff; 31]).collect::<Vec<_>>()[..]
+    );
+    let account = WideAccount { u: big, flag: 1 };
+    assert_eq!(std::mem::size_of::<WideAccount>(), 33);
+    assert_eq!(
+        anchor_lang::__private::bytemuck::bytes_of(&account)[..32],
+        big.to_le_bytes()
+    );
+}
+
+#[test]
+fn aliases_and_tuple_structs() {
+    let book = Book {
+        price: Price(3),
+        prices: vec![Price(1)],
+        ratio: 2,
+        pair: [1, 2],
+    };
+    let _copy: Price = book.price;
+    assert_eq!(book.try_to_vec().unwrap().len(), 8 + 4 + 8 + 8 + 2);
+    let counter = Counter(5, ID);
+    let mut data = Counter::discriminator().to_vec();
+    data.extend(counter.try_to_vec().unwrap());
+    match decoder::decode_account(&data) {
+        Some(decoder::ProgramAccount::Counter(c)) => assert_eq!(c.0, 5),
+        _ => panic!(),
+    }
+}
+
+#[allow(dead_code)]
+fn load_versioned<'info>(account: &Account<'info, Versioned>) -> u64 {
+    match **account {
+        Versioned::V1 { amount } => amount,
+        Versioned::V2(amount, _) => amount,
+    }
+}
+
+#[test]
+fn enum_payloads() {
+    // Variants are serialized as their index followed by their payload.
+    let named = Versioned::V1 { amount: 7 };
+    let bytes = named.try_to_vec().unwrap();
+    assert_eq!(bytes, [&[0][..], &7u64.to_le_bytes()].concat());
+    assert!(matches!(
+        Versioned::try_from_slice(&bytes).unwrap(),
+        Versioned::V1 { amount: 7 }
+    ));
+
+    let tuple = Versioned::V2(9, ID);
+    let bytes = tuple.try_to_vec().unwrap();
+    assert_eq!(bytes, [&[1][..], &9u64.to_le_bytes(), ID.as_ref()].concat());
+    assert_eq!(Versioned::try_from_slice(&bytes).unwrap(), tuple);
+    assert!(Versioned::try_from_slice(&bytes[..bytes.len() - 1]).is_err());
+
+    let filled = Slot::Filled {
+        value: 3u16,
+        version: 1,
+    };
+    let bytes = filled.try_to_vec().unwrap();
+    assert_eq!(bytes, [1, 3, 0, 1]);
+    assert!(matches!(
+        Slot::<u16>::try_from_slice(&bytes).unwrap(),
+        Slot::Filled {
+            value: 3,
+            version: 1
+        }
+    ));
+    assert_eq!(Slot::<u16>::Empty.try_to_vec().unwrap(), [0]);
+}
+
+#[test]
+fn enum_defaults() {
+    /// A payload which does not implement `Default`.
+    #[derive(Clone, Debug)]
+    struct NoDefault;
+
+    // The default variant carries no `T`, so `T` needs not implement `Default`.
+    assert!(matches!(Slot::<NoDefault>::default(), Slot::Empty));
+    assert!(matches!(Versioned::default(), Versioned::V1 { amount: 0 }));
+}
+
+#[test]
+fn enum_account() {
+    use anchor_lang::{AccountDeserialize, AccountSerialize};
+    let state = Versioned::V2(9, ID);
+    let mut data = vec![];
+    state.try_serialize(&mut data).unwrap();
+    assert_eq!(data[..8], [5; 8]);
+    let loaded = Versioned::try_deserialize(&mut &data[..]).unwrap();
+    assert!(matches!(loaded, Versioned::V2(9, _)));
+    assert!(matches!(
+        decoder::decode_account(&data),
+        Some(decoder::ProgramAccount::Versioned(Versioned::V2(9, _)))
+    ));
+    data[0] = 0;
+    assert!(Versioned::try_deserialize(&mut &data[..]).is_err());
+}
+
+#[test]
+fn recursive_types() {
+    let tree = TreeNode {
+        value: 1,
+        children: vec![TreeNode::default()],
+    };
+    let list = List {
+        head: 1,
+        next: Some(Box::new(List {
+            head: 2,
+            next: None,
+        })),
+    };
+    let expr = Expr::Add {
+        lhs: Box::new(Expr::Lit(1)),
+        rhs: Box::new(Expr::Neg(Box::new(Expr::default()))),
+    };
+    let ping = Ping {
+        pong: Some(Box::new(Pong { n: 1, ping: None })),
+    };
+    let bytes = (tree, list, expr.clone(), ping).try_to_vec().unwrap();
+    let (tree, list, decoded, _): (TreeNode, List, Expr, Ping) =
+        AnchorDeserialize::try_from_slice(&bytes).unwrap();
+    assert_eq!(tree.children.len(), 1);
+    assert_eq!(list.next.unwrap().head, 2);
+    assert_eq!(decoded.try_to_vec().unwrap(), expr.try_to_vec().unwrap());
+}
+
+#[test]
+fn extra_attrs() {
+    let mut set = std::collections::HashSet::new();
+    set.insert(List {
+        head: 1,
+        next: None,
+    });
+    assert!(set.contains(&List {
+        head: 1,
+        next: None
+    }));
+    assert_eq!(Versioned::V2(1, ID), Versioned::V2(1, ID));
+    assert_eq!(Expr::Lit(1), Expr::Lit(1));
+    assert!(WideAccount::default() == WideAccount::default());
+}
+
+#[test]
+fn type_overrides() {
+    let book = Book {
+        price: shared::Price(3),
+        prices: vec![shared::Price(1)],
+        ratio: 2,
+        pair: [1, 2],
+    };
+    let bytes = book.try_to_vec().unwrap();
+    assert_eq!(&bytes[..8], &3u64.to_le_bytes());
+    let decoded = Book::try_from_slice(&bytes).unwrap();
+    assert_eq!(decoded.prices, vec![shared::Price(1)]);
+}
+
+/// `WideAccount` is zero copy as its IDL layout is `bytemuck`.
+#[allow(dead_code)]
+fn load_wide(loader: &AccountLoader<WideAccount>) -> u8 {
+    loader.load().unwrap().flag
+}
+
+#[test]
+fn inferred_layouts() {
+    assert_eq!(std::mem::size_of::<PackedTick>(), 9);
+    assert_eq!(std::mem::align_of::<PackedTick>(), 1);
+    assert_eq!(std::mem::align_of::<AlignedSlot>(), 16);
+    // The wrappers have the layout of `u128` and `i128` on Solana.
+    assert_eq!(std::mem::offset_of!(WideCounter, value), 8);
+    assert_eq!(std::mem::size_of::<WideCounter>(), 72);
+    let counter = WideCounter {
+        flag: 1,
+        value: PodU128::new(u128::MAX - 1),
+        tick: PackedTick {
+            flag: 0,
+            liquidity: 3,
+        },
+        history: [(-5i128).into(), PodI128::new(i128::MIN)],
+    };
+    assert_eq!(u128::from(counter.value) + 1, u128::MAX);
+    assert_eq!(counter.history[0].get(), -5);
+    assert_eq!(i128::from(counter.history[1]), i128::MIN);
+    assert!(counter.history[1] < counter.history[0]);
+    assert_eq!(
+        counter.value.try_to_vec().unwrap(),
+        (u128::MAX - 1).to_le_bytes()
+    );
+    assert_eq!(format!("{:?}", counter.history[0]), "-5");
+    let tick = PackedTick {
+        flag: 1,
+        liquidity: 2,
+    };
+    let copy = tick;
+    assert_eq!({ copy.liquidity }, { tick.liquidity });
+}
+
+#[test]
+fn discriminators() {
+    let ix = client::build_initialize_user(
+        &client::accounts::InitializeUser::default(),
+        &client::args::InitializeUser {
+            params: InitParams {
+                sub_id: 1,
+                name: String::new(),
+            },
+            holder: holder(),
+        },
+        &[],
+    );
+    assert_eq!(
+        ix.data[..8],
+        anchor_lang::InstructionData::data(&instruction::InitializeUser {
+            _params: InitParams {
+                sub_id: 1,
+                name: String::new()
+            },
+            _holder: holder(),
+        })[..8]
+    );
+    assert_eq!(Market::discriminator(), sighash("account:Market"));
+}
+
+#[test]
+fn custom_discriminators() {
+    use anchor_lang::{AccountDeserialize, AccountSerialize};
+
+    assert_eq!(Counter::discriminator(), [1, 2, 3, 4, 5, 6, 7, 8]);
+    let mut data = Vec::new();
+    Counter(5, ID).try_serialize(&mut data).unwrap();
+    assert_eq!(data[..8], [1, 2, 3, 4, 5, 6, 7, 8]);
+    assert_eq!(Counter::try_deserialize(&mut &data[..]).unwrap().0, 5);
+    data[0] = 0;
+    assert!(Counter::try_deserialize(&mut &data[..]).is_err());
+
+    assert_eq!(WideAccount::discriminator(), [9; 8]);
+    let account = WideAccount {
+        u: U256::from(7u64),
+        flag: 1,
+    };
+    let mut data = [9; 8].to_vec();
+    data.extend_from_slice(anchor_lang::__private::bytemuck::bytes_of(&account));
+    let decoded = WideAccount::try_deserialize(&mut &data[..]).unwrap();
+    assert_eq!(decoded.flag, 1);
+    match decoder::decode_account(&data) {
+        Some(decoder::ProgramAccount::WideAccount(account)) => assert_eq!(account.flag, 1),
+        _ => panic!(),
+    }
+
+    assert_eq!(Deposited::discriminator(), [7; 8]);
+    let event = Deposited {
+        amount: 3,
+        owner: ID,
+    };
+    assert_eq!(anchor_lang::Event::data(&event)[..8], [7; 8]);
+}
+
+#[test]
+fn events_referenced_by_types() {
+    let owner = Pubkey::new_unique();
+    let receipt = Receipt {
+        event: Deposited { amount: 3, owner },
+        memo: "hi".to_string(),
+    };
+    let bytes = receipt.try_to_vec().unwrap();
+    assert_eq!(Receipt::try_from_slice(&bytes).unwrap().event.amount, 3);
+    let data = anchor_lang::Event::data(&receipt.event);
+    match events::ProgramEvent::decode(&data) {
+        Some(events::ProgramEvent::Deposited(event)) => assert_eq!(event.owner, owner),
+        _ => panic!(),
+    }
+}
+
+fn sighash(preimage: &str) -> [u8; 8] {
+    let hash = anchor_lang::solana_program::hash::hash(preimage.as_bytes());
+    hash.to_bytes()[..8].try_into().unwrap()
+}
@@ -0,0 +1,13 @@
+//! Autogenerated CPI client for the instructions of a program which are invoked.
+//!
+//! This crate was automatically generated by
+//! [anchor-gen](https://github.com/saber-hq/anchor-gen), a crate for generating
+//! Anchor CPI helpers from JSON IDLs.
+
+anchor_gen::generate_cpi_interface!(
+    idl_path = "idl.json",
+    instructions(include = [placeOrder]),
+    keep_types(Leverage),
+);
+
+declare_id!("whirLbMiicVdio4qvUfM5KAg6Ct8VwpYzGff3uctyCc");
@@ -0,0 +1,49 @@
+use anchor_lang::prelude::*;
+use filtered_cpi::*;
+
+#[test]
+fn included_instructions() {
+    let args = client::args::PlaceOrder {
+        params: OrderParams {
+            kind: OrderKind::Limit {
+                price: 10,
+                post_only: true,
+            },
+            trigger: Some(Trigger::Below(9)),
+            size: 3,
+        },
+        side: Side::Ask,
+    };
+    let decoded = decoder::Instruction::decode(&args.data()).unwrap();
+    assert_eq!(decoded.name(), "place_order");
+}
+
+#[test]
+fn excluded_instructions() {
+    for name in ["settle", "move"] {
+        let hash = anchor_lang::solana_program::hash::hash(format!("global:{}", name).as_bytes());
+        assert!(decoder::Instruction::decode(&hash.to_bytes()[..8]).is_none());
+    }
+}
+
+#[test]
+fn kept_typedefs() {
+    let leverage = Leverage::Dynamic { min: 1, max: 5 };
+    let bytes = leverage.try_to_vec().unwrap();
+    assert!(matches!(
+        Leverage::try_from_slice(&bytes).unwrap(),
+        Leverage::Dynamic { min: 1, max: 5 }
+    ));
+    assert_eq!(Leverage::default().try_to_vec().unwrap(), [0, 0]);
+}
+
+#[test]
+fn accounts_and_events() {
+    let market = Market {
+        authority: Pubkey::default(),
+        last_order: Some(OrderParams::default()),
+    };
+    assert!(market.last_order.is_some());
+    let _ = events::Moved { distance: 1 };
+    assert_eq!(constants::FEE_BPS, 30);
+}